@@ -0,0 +1,540 @@
+use std::{
+	collections::{BTreeMap, HashSet},
+	fs,
+	ops::Range,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	check::{url_spans, urls_in},
+	util::{copy_dir, files_in, fnv1a, url_to_static_path},
+	Result,
+};
+
+/// Files with these extensions are warned about when they're over the size
+/// limit, since they're what ends up making pages slow to load.
+const MEDIA_EXTENSIONS: [&str; 10] = [
+	"png", "jpg", "jpeg", "gif", "webp", "avif", "mp4", "webm", "mp3", "ogg",
+];
+
+/// Copy `static_dir` into `output/static`, reporting files no page in
+/// `output` or the stylesheet uses, and media over `max_size` bytes. With
+/// `prune` set, unused files are left out of the output (and removed from it,
+/// if an earlier build copied them).
+pub fn copy_static<A: AsRef<Path>, B: AsRef<Path>, C: AsRef<Path>>(
+	static_dir: A,
+	output: B,
+	stylesheet: C,
+	prune: bool,
+	max_size: u64,
+) -> Result<()> {
+	let static_dir = static_dir.as_ref();
+	let output_static = output.as_ref().join("static");
+	let used = used_static_files(&output, stylesheet)?;
+	let is_used = |file: &Path, dir: &Path| {
+		used.contains(file.strip_prefix(dir).unwrap_or(file))
+	};
+
+	for file in files_in(static_dir)? {
+		if !is_used(&file, static_dir) {
+			println!("⚠️  Unused static file {}", file.display());
+		}
+		let size = fs::metadata(&file)?.len();
+		if size > max_size && is_media(&file) {
+			println!(
+				"⚠️  Large static file {} ({})",
+				file.display(),
+				pretty_size(size)
+			);
+		}
+	}
+
+	if prune {
+		copy_dir(static_dir, &output_static, &|file| {
+			is_used(file, static_dir)
+		})?;
+		// The output directory sticks around between builds.
+		for file in files_in(&output_static)? {
+			if !is_used(&file, &output_static) {
+				fs::remove_file(file)?;
+			}
+		}
+	} else {
+		copy_dir(static_dir, &output_static, &|_| true)?;
+	}
+
+	Ok(())
+}
+
+/// Every file in `./static` referenced from a page in `output` or from the
+/// stylesheet, by its path in there.
+fn used_static_files<A: AsRef<Path>, B: AsRef<Path>>(
+	output: A,
+	stylesheet: B,
+) -> Result<HashSet<PathBuf>> {
+	let mut urls = css_urls(&fs::read_to_string(stylesheet)?);
+
+	for page in files_in(&output)? {
+		if page.starts_with(output.as_ref().join("static"))
+			|| page.extension().is_none_or(|ext| ext != "html")
+		{
+			continue;
+		}
+		urls.extend(urls_in(&fs::read_to_string(&page)?));
+	}
+
+	Ok(
+		urls
+			.iter()
+			.filter_map(|url| {
+				let path = url_to_static_path(url)?;
+				Some(path.strip_prefix("./static").ok()?.to_path_buf())
+			})
+			.collect(),
+	)
+}
+
+/// Every `url(...)` in a stylesheet.
+fn css_urls(css: &str) -> Vec<String> {
+	css_url_spans(css).into_iter().map(|(_, url)| url).collect()
+}
+
+/// The urls from [`css_urls`], along with where each one is in the css.
+fn css_url_spans(css: &str) -> Vec<(Range<usize>, String)> {
+	css
+		.match_indices("url(")
+		.filter_map(|(i, open)| {
+			let start = i + open.len();
+			let inner = &css[start..start + css[start..].find(')')?];
+			let url = inner.trim().trim_matches(['"', '\'']);
+			let leading = inner.trim_start().trim_start_matches(['"', '\'']);
+			let start = start + inner.len() - leading.len();
+			Some((start..start + url.len(), url.to_string()))
+		})
+		.collect()
+}
+
+/// Content-hashed names for the files in `./static`, so a host can cache them
+/// forever: `/static/starheart.gif` is also copied to
+/// `/static/starheart.<hash>.gif`, and pages link to that instead. The
+/// originals stay where they were for the feed and links from other sites.
+///
+/// Only absolute `/static/...` urls are rewritten, and only where they're
+/// really urls: the attributes [`urls_in`] finds in html, and `url(...)`s in
+/// css. A stylesheet in `static/` is hashed after its own urls are, so it
+/// changes name when they do.
+#[derive(Default)]
+pub struct AssetManifest {
+	/// The hash of each file in `./static`, by its path in there.
+	hashes: BTreeMap<PathBuf, String>,
+	/// Stylesheets with their urls rewritten, which are copied instead of the
+	/// originals.
+	css: BTreeMap<PathBuf, String>,
+}
+
+impl AssetManifest {
+	pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self> {
+		let dir = dir.as_ref();
+		let mut manifest = Self::default();
+		let (css, other): (Vec<_>, Vec<_>) = files_in(dir)?
+			.into_iter()
+			.partition(|file| file.extension().is_some_and(|ext| ext == "css"));
+		let name =
+			|file: &Path| file.strip_prefix(dir).unwrap_or(file).to_path_buf();
+		for file in other {
+			let hash = format!("{:016x}", fnv1a(&fs::read(&file)?));
+			manifest.hashes.insert(name(&file), hash);
+		}
+		for file in css {
+			let rewritten = manifest.rewrite_css(&fs::read_to_string(&file)?);
+			let hash = format!("{:016x}", fnv1a(rewritten.as_bytes()));
+			manifest.hashes.insert(name(&file), hash);
+			manifest.css.insert(name(&file), rewritten);
+		}
+		Ok(manifest)
+	}
+
+	/// Point the `/static/...` urls in some html's attributes at the hashed
+	/// copies. Anything after the path, like `?v=1` or `#top`, is kept.
+	pub fn rewrite_html(&self, html: &str) -> String {
+		self.rewrite(html, url_spans(html))
+	}
+
+	/// Like [`rewrite_html`](Self::rewrite_html), for the `url(...)`s in css.
+	pub fn rewrite_css(&self, css: &str) -> String {
+		self.rewrite(css, css_url_spans(css))
+	}
+
+	fn rewrite(&self, text: &str, urls: Vec<(Range<usize>, String)>) -> String {
+		let mut out = String::with_capacity(text.len());
+		let mut last = 0;
+		for (range, url) in urls {
+			let hash = url_to_static_path(&url)
+				.and_then(|path| self.hashes.get(path.strip_prefix("./static").ok()?));
+			let Some(hash) = hash else {
+				continue;
+			};
+			// Html urls are still escaped here, but only the path changes.
+			let raw = &text[range.clone()];
+			let path_end = raw.find(['?', '#']).unwrap_or(raw.len());
+			out.push_str(&text[last..range.start]);
+			out.push_str(&hashed(&raw[..path_end], hash));
+			out.push_str(&raw[path_end..]);
+			last = range.end;
+		}
+		out.push_str(&text[last..]);
+		out
+	}
+
+	/// Rewrite the pages in `output`, copy each file from `static_dir` that
+	/// made it into the output to its hashed name, and list them all in
+	/// `asset-manifest.json`. Hashed copies from earlier builds are removed,
+	/// so an empty manifest just cleans up after fingerprinting.
+	pub fn write<A: AsRef<Path>, B: AsRef<Path>>(
+		&self,
+		static_dir: A,
+		output: B,
+	) -> Result<()> {
+		let output = output.as_ref();
+		let output_static = output.join("static");
+		let mut urls = BTreeMap::new();
+		for (name, hash) in &self.hashes {
+			let copied = output_static.join(name);
+			if !copied.is_file() {
+				// Pruned.
+				continue;
+			}
+			let url =
+				format!("/static/{}", name.to_string_lossy().replace('\\', "/"));
+			let hashed_url = hashed(&url, hash);
+			let hashed_copy = output.join(hashed_url.trim_start_matches('/'));
+			match self.css.get(name) {
+				Some(css) => fs::write(&hashed_copy, css)?,
+				None => {
+					fs::copy(&copied, &hashed_copy)?;
+				}
+			}
+			urls.insert(url, hashed_url);
+		}
+
+		// The output directory sticks around between builds.
+		let written = urls
+			.values()
+			.map(|url| output.join(url.trim_start_matches('/')))
+			.collect::<HashSet<_>>();
+		for file in files_in(&output_static)? {
+			if !written.contains(&file)
+				&& unhashed(&file).is_some_and(|original| {
+					let name = original.strip_prefix(&output_static).unwrap_or(&original);
+					static_dir.as_ref().join(name).is_file()
+				}) {
+				fs::remove_file(file)?;
+			}
+		}
+
+		let manifest = output.join("asset-manifest.json");
+		if urls.is_empty() {
+			if manifest.is_file() {
+				fs::remove_file(manifest)?;
+			}
+			return Ok(());
+		}
+
+		for page in files_in(output)? {
+			let rewrite = match page.extension().and_then(|ext| ext.to_str()) {
+				_ if page.starts_with(&output_static) => continue,
+				Some("html") => Self::rewrite_html,
+				Some("css") => Self::rewrite_css,
+				_ => continue,
+			};
+			let text = fs::read_to_string(&page)?;
+			let rewritten = rewrite(self, &text);
+			if rewritten != text {
+				fs::write(&page, rewritten)?;
+			}
+		}
+
+		println!("🏷️  Fingerprinted {} static files", urls.len());
+		fs::write(manifest, manifest_json(&urls))?;
+		Ok(())
+	}
+}
+
+/// `/static/fonts/a.woff2` to `/static/fonts/a.<hash>.woff2`.
+fn hashed(url: &str, hash: &str) -> String {
+	let name_start = url.rfind('/').map_or(0, |i| i + 1);
+	match url[name_start..].rfind('.') {
+		Some(dot) if dot > 0 => {
+			let dot = name_start + dot;
+			format!("{}.{}{}", &url[..dot], hash, &url[dot..])
+		}
+		_ => format!("{}.{}", url, hash),
+	}
+}
+
+/// The file a hashed copy was made from, if `file` looks like one.
+fn unhashed(file: &Path) -> Option<PathBuf> {
+	let mut parts = file.file_name()?.to_str()?.split('.').collect::<Vec<_>>();
+	let hash = parts.iter().rposition(|part| is_hash(part))?;
+	if hash == 0 || hash + 2 < parts.len() {
+		return None;
+	}
+	parts.remove(hash);
+	Some(file.with_file_name(parts.join(".")))
+}
+
+pub fn is_hash(s: &str) -> bool {
+	s.len() == 16 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// The manifest as json, one url to its hashed copy per line.
+fn manifest_json(urls: &BTreeMap<String, String>) -> String {
+	let string = |s: &str| {
+		let mut out = String::from('"');
+		for c in s.chars() {
+			match c {
+				'"' => out.push_str("\\\""),
+				'\\' => out.push_str("\\\\"),
+				c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+				c => out.push(c),
+			}
+		}
+		out.push('"');
+		out
+	};
+	let entries = urls
+		.iter()
+		.map(|(url, hashed)| format!("  {}: {}", string(url), string(hashed)))
+		.collect::<Vec<_>>();
+	format!("{{\n{}\n}}\n", entries.join(",\n"))
+}
+
+fn is_media(file: &Path) -> bool {
+	file
+		.extension()
+		.map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+		.is_some_and(|ext| MEDIA_EXTENSIONS.contains(&ext.as_str()))
+}
+
+fn pretty_size(bytes: u64) -> String {
+	if bytes >= 1024 * 1024 {
+		format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+	} else {
+		format!("{:.1} KB", bytes as f64 / 1024.0)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// A new directory for a test to write files in.
+	fn temp_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!(
+			"assets-{}-{}",
+			name,
+			std::process::id()
+		));
+		if dir.exists() {
+			fs::remove_dir_all(&dir).unwrap();
+		}
+		dir
+	}
+
+	fn write(path: &Path, contents: &str) {
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(path, contents).unwrap();
+	}
+
+	#[test]
+	fn test_used_static_files() {
+		let dir = temp_dir("used");
+		let (output, stylesheet) = (dir.join("output"), dir.join("styles.css"));
+		write(
+			&stylesheet,
+			"@font-face { src: url('/static/fonts/a.woff2'); }",
+		);
+		write(
+			&output.join("posts/a.html"),
+			r#"<img src="/static/Untitled%201.png" srcset="/static/b-300.png 300w, /static/b-600.png?v=2 600w">
+			<video poster="/static/c.jpg"><source src=/static/c.mp4></video>
+			<a href="https://a.com/static/d.png">/static/e.png</a>"#,
+		);
+		// Only pages count, not what's in the static files themselves.
+		write(
+			&output.join("static/f.html"),
+			r#"<img src="/static/f.png">"#,
+		);
+		write(&output.join("feed.xml"), "<url>/static/g.png</url>");
+
+		let mut used = used_static_files(&output, &stylesheet)
+			.unwrap()
+			.into_iter()
+			.collect::<Vec<_>>();
+		used.sort();
+		assert_eq!(
+			used,
+			[
+				"Untitled 1.png",
+				"b-300.png",
+				"b-600.png",
+				"c.jpg",
+				"c.mp4",
+				"fonts/a.woff2",
+			]
+			.map(PathBuf::from)
+		);
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn test_prune() {
+		let dir = temp_dir("prune");
+		let (static_dir, output) = (dir.join("static"), dir.join("output"));
+		let stylesheet = dir.join("styles.css");
+		write(&stylesheet, "");
+		for file in ["a.png", "unused.png", "fonts/b.woff2"] {
+			write(&static_dir.join(file), file);
+		}
+		write(
+			&output.join("index.html"),
+			r#"<img src="/static/a.png"><link href="/static/fonts/b.woff2">"#,
+		);
+		// Copied by an earlier build, and not used any more.
+		write(&output.join("static/old.png"), "");
+		let copied = || {
+			files_in(output.join("static"))
+				.unwrap()
+				.iter()
+				.map(|file| {
+					file
+						.strip_prefix(output.join("static"))
+						.unwrap()
+						.to_path_buf()
+				})
+				.collect::<Vec<_>>()
+		};
+
+		copy_static(&static_dir, &output, &stylesheet, true, 1024).unwrap();
+		assert_eq!(copied(), ["a.png", "fonts/b.woff2"].map(PathBuf::from));
+
+		copy_static(&static_dir, &output, &stylesheet, false, 1024).unwrap();
+		assert_eq!(
+			copied(),
+			["a.png", "fonts/b.woff2", "unused.png"].map(PathBuf::from)
+		);
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn test_css_urls() {
+		let css = r#"@font-face { src: url("/static/a.woff2") format("woff2"); }
+			.b { background: url( '/static/b.png' ), url(/static/c.png); }"#;
+		assert_eq!(
+			css_urls(css),
+			["/static/a.woff2", "/static/b.png", "/static/c.png"]
+		);
+	}
+
+	#[test]
+	fn test_fingerprint_urls() {
+		let manifest = AssetManifest {
+			hashes: BTreeMap::from([
+				(
+					PathBuf::from("starheart.gif"),
+					"0123456789abcdef".to_string(),
+				),
+				(
+					PathBuf::from("embeds/post/Untitled 1.png"),
+					"fedcba9876543210".to_string(),
+				),
+			]),
+			..Default::default()
+		};
+		assert_eq!(
+			manifest.rewrite_html(
+				r#"<img src="/static/starheart.gif"><img srcset="/static/embeds/post/Untitled%201.png?x&amp;y 2x, /static/missing.png 1x"><a href="https://a.com/static/starheart.gif">"#
+			),
+			r#"<img src="/static/starheart.0123456789abcdef.gif"><img srcset="/static/embeds/post/Untitled%201.fedcba9876543210.png?x&amp;y 2x, /static/missing.png 1x"><a href="https://a.com/static/starheart.gif">"#
+		);
+		// Only urls are rewritten, not text that happens to look like one.
+		let text = r#"<p>see /static/starheart.gif, or "/static/starheart.gif"</p>
+			<pre>src=/static/starheart.gif</pre>
+			<style>a { background: url(/static/starheart.gif) }</style>"#;
+		assert_eq!(manifest.rewrite_html(text), text);
+		assert_eq!(
+			manifest.rewrite_css(
+				".a { background: url( '/static/starheart.gif#x' ) } /* /static/starheart.gif */"
+			),
+			".a { background: url( '/static/starheart.0123456789abcdef.gif#x' ) } /* /static/starheart.gif */"
+		);
+		assert_eq!(
+			unhashed(Path::new("./output/static/a.0123456789abcdef.gif")),
+			Some(PathBuf::from("./output/static/a.gif"))
+		);
+		assert_eq!(unhashed(Path::new("./output/static/a.gif")), None);
+	}
+
+	#[test]
+	fn test_write() {
+		let dir = temp_dir("write");
+		let (static_dir, output) = (dir.join("static"), dir.join("output"));
+		write(&static_dir.join("a.gif"), "gif");
+		write(
+			&static_dir.join("b.css"),
+			".b { background: url(/static/a.gif) }",
+		);
+		write(&static_dir.join("pruned.png"), "png");
+		write(&output.join("static/a.gif"), "gif");
+		write(&output.join("static/b.css"), "");
+		// An earlier build's copy of a.gif, and a file that only looks hashed.
+		write(&output.join("static/a.0000000000000000.gif"), "old");
+		write(&output.join("static/c.0123456789abcdef.txt"), "c");
+		let page = r#"<img src="/static/a.gif"><p>/static/a.gif</p>"#;
+		write(&output.join("index.html"), page);
+
+		let manifest = AssetManifest::load(&static_dir).unwrap();
+		manifest.write(&static_dir, &output).unwrap();
+		let a = hashed("/static/a.gif", &manifest.hashes[Path::new("a.gif")]);
+		let b = hashed("/static/b.css", &manifest.hashes[Path::new("b.css")]);
+		let read = |url: &str| {
+			fs::read_to_string(output.join(url.trim_start_matches('/'))).unwrap()
+		};
+		assert_eq!(read(&a), "gif");
+		assert_eq!(read(&b), format!(".b {{ background: url({}) }}", a));
+		assert_eq!(
+			read("index.html"),
+			format!(r#"<img src="{}"><p>/static/a.gif</p>"#, a)
+		);
+		assert_eq!(
+			read("asset-manifest.json"),
+			format!(
+				"{{\n  \"/static/a.gif\": \"{}\",\n  \"/static/b.css\": \"{}\"\n}}\n",
+				a, b
+			)
+		);
+		let files = files_in(output.join("static")).unwrap();
+		let names = files
+			.iter()
+			.map(|file| file.file_name().unwrap().to_str().unwrap())
+			.collect::<Vec<_>>();
+		let mut expected = vec![
+			"a.gif",
+			a.trim_start_matches("/static/"),
+			"b.css",
+			b.trim_start_matches("/static/"),
+			"c.0123456789abcdef.txt",
+		];
+		expected.sort();
+		assert_eq!(names, expected);
+
+		// Turning fingerprinting off cleans up after it.
+		AssetManifest::default()
+			.write(&static_dir, &output)
+			.unwrap();
+		assert!(!output.join("asset-manifest.json").exists());
+		assert_eq!(files_in(output.join("static")).unwrap().len(), 3);
+
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
@@ -1,35 +1,183 @@
-use crate::{parsers::date::Date, post::Post, Result};
+use crate::{
+	page_builder::PageBuilder, pagination::Page, parsers::date::Date, post::Post,
+	Result,
+};
 use maud::{html, Markup};
+use std::collections::BTreeMap;
 
-pub fn blog_list(posts: &Vec<Post>) -> Result<Markup> {
-	let mut items = Vec::<BlogListItem>::new();
+pub fn blog_list(page: &Page<Post>) -> Result<Markup> {
+	Ok(html! {
+		(list_links("/blog.html"))
+		(post_list_with_dates(page.items))
+		(page.nav())
+	})
+}
+
+/// `archive.html`, with a page for each year in `archive/2024.html` and each
+/// month in `archive/2024/01.html`.
+pub fn gen_archive_pages(builder: &PageBuilder, posts: &[Post]) -> Result<()> {
+	let years = group_by_date(posts);
+
+	builder
+		.clone()
+		.title("archive - john mcparland")
+		.body(archive_index(&years))
+		.write("archive.html")?;
+
+	for (year, months) in &years {
+		let posts = months.values().rev().flatten().copied().collect::<Vec<_>>();
+		builder
+			.clone()
+			.title(&format!("{} - john mcparland", year))
+			.body(html! {
+				(list_links(""))
+				p {
+					(plural(posts.len())) " from "
+					b { (year) }
+				}
+				(post_list_with_dates(posts))
+			})
+			.write(year_href(*year).trim_start_matches('/'))?;
 
-	let mut year: u16 = posts[0].date.year;
-	let mut month: u16 = posts[0].date.month;
+		for (month, posts) in months {
+			let date = Date::new(*year, *month, 1);
+			builder
+				.clone()
+				.title(&format!("{} - john mcparland", date.pretty_no_day()))
+				.body(html! {
+					(list_links(""))
+					p {
+						"All posts from "
+						b { (date.month_name()) }
+						" "
+						a href=(year_href(*year)) { (year) }
+					}
+					.post-list {
+						@for post in posts {
+							(post.as_list_item())
+						}
+					}
+				})
+				.write(month_href(*year, *month).trim_start_matches('/'))?;
+		}
+	}
+
+	Ok(())
+}
 
-	items.push(BlogListItem::Header { year, month });
+/// Posts by year and then month, newest first.
+type Years<'a> = Vec<(u16, BTreeMap<u16, Vec<&'a Post>>)>;
 
+fn group_by_date(posts: &[Post]) -> Years<'_> {
+	let mut years: BTreeMap<u16, BTreeMap<u16, Vec<&Post>>> = BTreeMap::new();
 	for post in posts {
-		if post.date.year != year || post.date.month != month {
-			year = post.date.year;
-			month = post.date.month;
-			items.push(BlogListItem::Header { year, month });
+		years
+			.entry(post.date.year)
+			.or_default()
+			.entry(post.date.month)
+			.or_default()
+			.push(post);
+	}
+	years
+		.into_iter()
+		.rev()
+		.map(|(year, mut months)| {
+			for posts in months.values_mut() {
+				posts.sort_by(|a, b| b.cmp(a));
+			}
+			(year, months)
+		})
+		.collect()
+}
+
+fn archive_index(years: &Years) -> Markup {
+	html! {
+		(list_links("/archive.html"))
+		ul.archive-index {
+			@for (year, months) in years {
+				li {
+					a href=(year_href(*year)) { (year) }
+					" - ";
+					(plural(months.values().map(Vec::len).sum()))
+					ul {
+						@for (month, posts) in months.iter().rev() {
+							li {
+								a href=(month_href(*year, *month)) {
+									(Date::new(*year, *month, 1).month_name())
+								}
+								" - ";
+								(plural(posts.len()))
+							}
+						}
+					}
+				}
+			}
 		}
+	}
+}
 
-		items.push(BlogListItem::Post(post));
+fn plural(count: usize) -> String {
+	match count {
+		1 => "1 post".to_string(),
+		count => format!("{} posts", count),
 	}
+}
 
-	Ok(html! {
+fn year_href(year: u16) -> String {
+	format!("/archive/{}.html", year)
+}
+
+fn month_href(year: u16, month: u16) -> String {
+	format!("/archive/{}/{:02}.html", year, month)
+}
+
+/// The links at the top of each list of posts, with the one to `current`
+/// left as plain text.
+pub fn list_links(current: &str) -> Markup {
+	let links = [
+		("/blog.html", "Posts by date"),
+		("/tags.html", "Posts by tag"),
+		("/archive.html", "Archive"),
+	];
+	html! {
 		p {
-			"Posts by date"
-			" - ";
-			a href="/tags.html" { "Posts by tag" }
+			@for (i, (href, label)) in links.into_iter().enumerate() {
+				@if i > 0 { " - " }
+				@if href == current {
+					(label)
+				} @else {
+					a href=(href) { (label) }
+				}
+			}
+		}
+	}
+}
+
+/// Posts under a header for each month, linking to the month's archive page.
+pub fn post_list_with_dates<'a>(
+	posts: impl IntoIterator<Item = &'a Post>,
+) -> Markup {
+	let mut items = Vec::<BlogListItem>::new();
+	let mut current = None;
+
+	for post in posts {
+		if current != Some((post.date.year, post.date.month)) {
+			let (year, month) = (post.date.year, post.date.month);
+			current = Some((year, month));
+			items.push(BlogListItem::Header { year, month });
 		}
+
+		items.push(BlogListItem::Post(post));
+	}
+
+	html! {
 		.post-list.post-list-with-dates {
 			@for item in items {
 				@match item {
 					BlogListItem::Header { year, month } => h2.post-list-date-header {
-						(Date::new(year, month, 1).pretty_no_day())
+						a href=(month_href(year, month)) {
+							(Date::new(year, month, 1).pretty_no_day())
+						}
 					},
 					BlogListItem::Post(post) => {
 						(post.as_list_item())
@@ -37,10 +185,67 @@ pub fn blog_list(posts: &Vec<Post>) -> Result<Markup> {
 				}
 			}
 		}
-	})
+	}
 }
 
 enum BlogListItem<'a> {
 	Header { year: u16, month: u16 },
 	Post(&'a Post),
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::post::test_post;
+
+	fn posts() -> Vec<Post> {
+		vec![
+			test_post("a", Date::new(2023, 12, 30)),
+			test_post("b", Date::new(2024, 1, 5)),
+			test_post("c", Date::new(2024, 1, 20)),
+			test_post("d", Date::new(2024, 11, 2)),
+			test_post("e", Date::new(2024, 1, 20)),
+		]
+	}
+
+	#[test]
+	fn test_group_by_date() {
+		let posts = posts();
+		let years = group_by_date(&posts);
+		assert_eq!(
+			years.iter().map(|(year, _)| *year).collect::<Vec<_>>(),
+			[2024, 2023]
+		);
+		let (_, months) = &years[0];
+		assert_eq!(months.keys().copied().collect::<Vec<_>>(), [1, 11]);
+		// Newest first, and the same day by file name, newest first too.
+		assert_eq!(
+			months[&1]
+				.iter()
+				.map(|post| post.filename.as_str())
+				.collect::<Vec<_>>(),
+			["e", "c", "b"]
+		);
+		assert!(group_by_date(&[]).is_empty());
+	}
+
+	#[test]
+	fn test_archive_index() {
+		let posts = posts();
+		let index = archive_index(&group_by_date(&posts)).0;
+		let expected = [
+			r#"<a href="/archive/2024.html">2024</a> - 4 posts"#,
+			r#"<a href="/archive/2024/11.html">November</a> - 1 post"#,
+			r#"<a href="/archive/2024/01.html">January</a> - 3 posts"#,
+			r#"<a href="/archive/2023.html">2023</a> - 1 post"#,
+			r#"<a href="/archive/2023/12.html">December</a> - 1 post"#,
+		];
+		let positions = expected
+			.iter()
+			.map(|html| index.find(html).unwrap_or_else(|| panic!("{}", html)))
+			.collect::<Vec<_>>();
+		assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+		// The archive is the current page in the links at the top.
+		assert!(index.contains("Posts by tag</a> - Archive</p>"));
+	}
+}
@@ -0,0 +1,310 @@
+use std::{
+	collections::BTreeMap,
+	fmt,
+	ops::Range,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	error::Error,
+	util::{files_in, percent_decode},
+	Result,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct BrokenLink {
+	/// The post the link was written in, or the page if it wasn't from a post.
+	pub source: PathBuf,
+	pub url: String,
+}
+
+impl fmt::Display for BrokenLink {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: {}", self.source.display(), self.url)
+	}
+}
+
+/// Check every `href` and `src` in the html written to `output` points to a
+/// file that exists. With `list_external` set, every link to another site is
+/// printed as well, for checking by hand.
+pub fn check<P: AsRef<Path>>(output: P, list_external: bool) -> Result<()> {
+	let output = output.as_ref();
+	let mut broken = Vec::new();
+	let mut external: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+	for page in files_in(output)? {
+		if page.extension().is_none_or(|ext| ext != "html") {
+			continue;
+		}
+		let html = std::fs::read_to_string(&page)?;
+		let source = source_of(output, &page);
+
+		for url in urls_in(&html) {
+			match Link::parse(&url) {
+				Link::External => {
+					let pages = external.entry(url).or_default();
+					if !pages.contains(&source) {
+						pages.push(source.clone());
+					}
+				}
+				Link::Internal(path) => {
+					let link = BrokenLink {
+						source: source.clone(),
+						url,
+					};
+					if !resolve(output, &page, &path).exists() && !broken.contains(&link)
+					{
+						broken.push(link);
+					}
+				}
+				Link::Ignored => {}
+			}
+		}
+	}
+
+	if list_external {
+		println!("🌐 External links");
+		for (url, pages) in &external {
+			println!("{}", url);
+			for page in pages {
+				println!("    {}", page.display());
+			}
+		}
+	}
+
+	if broken.is_empty() {
+		println!("✅ No broken links");
+		Ok(())
+	} else {
+		println!("❌ Broken links");
+		for link in &broken {
+			println!("{}", link);
+		}
+		Err(Error::BrokenLinks(broken))
+	}
+}
+
+/// Posts are written to `posts/<name>.html`, so broken links there are
+/// reported against the markdown file they came from.
+fn source_of(output: &Path, page: &Path) -> PathBuf {
+	let relative = page.strip_prefix(output).unwrap_or(page);
+	if relative.parent() == Some(Path::new("posts")) {
+		if let Some(stem) = relative.file_stem() {
+			let post = Path::new("./posts").join(stem).with_extension("md");
+			if post.is_file() {
+				return post;
+			}
+		}
+	}
+	page.to_path_buf()
+}
+
+/// Where a link's path ends up on disk, relative to the page it's on when it
+/// doesn't start with a `/`.
+fn resolve(output: &Path, page: &Path, path: &str) -> PathBuf {
+	let file = match path.strip_prefix('/') {
+		Some(path) => output.join(path),
+		None => page.parent().unwrap_or(output).join(path),
+	};
+	if file.is_dir() {
+		file.join("index.html")
+	} else {
+		file
+	}
+}
+
+#[derive(Debug, PartialEq)]
+enum Link {
+	External,
+	/// The decoded path of a link on this site, without any query or fragment.
+	Internal(String),
+	/// Links within the same page, email addresses and such.
+	Ignored,
+}
+
+impl Link {
+	fn parse(url: &str) -> Self {
+		if url.starts_with("http://")
+			|| url.starts_with("https://")
+			|| url.starts_with("//")
+		{
+			return Self::External;
+		}
+		// Any other scheme, like `mailto:` or `data:`.
+		let scheme = url.split_once(':').map(|(scheme, _)| scheme);
+		if scheme.is_some_and(|scheme| {
+			!scheme.is_empty()
+				&& scheme
+					.chars()
+					.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+		}) {
+			return Self::Ignored;
+		}
+		let path = url.split(['?', '#']).next().unwrap_or("");
+		if path.is_empty() {
+			return Self::Ignored;
+		}
+		Self::Internal(percent_decode(path))
+	}
+}
+
+/// Every `href`, `src` and `poster` attribute value in some html, and each
+/// candidate url in a `srcset`, unescaped.
+pub fn urls_in(html: &str) -> Vec<String> {
+	url_spans(html).into_iter().map(|(_, url)| url).collect()
+}
+
+/// The urls from [`urls_in`], along with where each one is in the html.
+pub fn url_spans(html: &str) -> Vec<(Range<usize>, String)> {
+	let mut urls = Vec::new();
+	let mut rest = html;
+
+	while let Some(start) = rest.find('<') {
+		rest = &rest[start + 1..];
+		if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+			continue;
+		}
+		let name_len = rest
+			.find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+			.unwrap_or(rest.len());
+		let tag = rest[..name_len].to_ascii_lowercase();
+		rest = &rest[name_len..];
+
+		loop {
+			rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+			if rest.is_empty() || rest.starts_with('>') {
+				break;
+			}
+			let attr_len = rest
+				.find(|c: char| c.is_whitespace() || c == '=' || c == '>')
+				.unwrap_or(rest.len());
+			let attr = rest[..attr_len].to_ascii_lowercase();
+			rest = &rest[attr_len..];
+			let Some(after_eq) = rest.trim_start().strip_prefix('=') else {
+				continue;
+			};
+			let after_eq = after_eq.trim_start();
+			let quoted = after_eq.starts_with(['"', '\'']);
+			let value_start = html.len() - after_eq.len() + quoted as usize;
+			let (value, after) = match after_eq.chars().next() {
+				Some(quote @ ('"' | '\'')) => {
+					let inner = &after_eq[1..];
+					let end = inner.find(quote).unwrap_or(inner.len());
+					(&inner[..end], inner.get(end + 1..).unwrap_or(""))
+				}
+				_ => {
+					let end = after_eq
+						.find(|c: char| c.is_whitespace() || c == '>')
+						.unwrap_or(after_eq.len());
+					(&after_eq[..end], &after_eq[end..])
+				}
+			};
+			rest = after;
+			if attr == "href" || attr == "src" || attr == "poster" {
+				urls.push((value_start..value_start + value.len(), unescape(value)));
+			} else if attr == "srcset" {
+				urls.extend(srcset_urls(value).into_iter().map(|range| {
+					let url = unescape(&value[range.clone()]);
+					(value_start + range.start..value_start + range.end, url)
+				}));
+			}
+		}
+
+		// Nothing inside these is markup.
+		if tag == "style" || tag == "script" {
+			let close = format!("</{}", tag);
+			rest = rest.find(&close).map_or("", |end| &rest[end..]);
+		}
+	}
+
+	urls
+}
+
+/// Where the urls are in a `srcset` like `/a-640w.jpg 640w, /a-1280w.jpg
+/// 1280w`. A url runs up to whitespace, and can have commas in it, so each
+/// candidate ends at the first comma after its url instead.
+fn srcset_urls(srcset: &str) -> Vec<Range<usize>> {
+	let mut urls = Vec::new();
+	let mut rest = srcset;
+	loop {
+		rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+		if rest.is_empty() {
+			break;
+		}
+		let start = srcset.len() - rest.len();
+		let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+		let url = &rest[..end];
+		rest = &rest[end..];
+		if url.ends_with(',') {
+			urls.push(start..start + url.trim_end_matches(',').len());
+			continue;
+		}
+		urls.push(start..start + end);
+		// Skip the descriptors, like `640w` or `2x`.
+		rest = rest.find(',').map_or("", |comma| &rest[comma + 1..]);
+	}
+	urls
+}
+
+fn unescape(value: &str) -> String {
+	value
+		.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_urls_in() {
+		let html = r#"<style>a { background: url("<a href='nope'>") }</style>
+			<a class="x" href="/posts/a.html#top">a</a>
+			<img alt='x > y' src=/static/b%20c.png>
+			<video controls poster="/static/d.jpg"><source src="/static/d.mp4?x=1&amp;y=2"></video>
+			<picture><source type="image/webp" srcset="/images/e-640w.webp 640w, /images/e-1280w.webp 1280w">
+			<img srcset="/images/f,1.png, /images/f-2x.png 2x"></picture>
+			<p>href="not/an/attribute"</p>"#;
+		assert_eq!(
+			urls_in(html),
+			[
+				"/posts/a.html#top",
+				"/static/b%20c.png",
+				"/static/d.jpg",
+				"/static/d.mp4?x=1&y=2",
+				"/images/e-640w.webp",
+				"/images/e-1280w.webp",
+				"/images/f,1.png",
+				"/images/f-2x.png",
+			]
+		);
+	}
+
+	#[test]
+	fn test_url_spans() {
+		let html = r#"<img src=/a.png srcset="/b.png 2x, /c.png?x&amp;y 3x">"#;
+		let spans = url_spans(html);
+		for (range, url) in &spans {
+			assert_eq!(&html[range.clone()].replace("&amp;", "&"), url);
+		}
+		assert_eq!(spans.len(), 3);
+	}
+
+	#[test]
+	fn test_link_parse() {
+		assert_eq!(Link::parse("https://mcpar.land"), Link::External);
+		assert_eq!(Link::parse("mailto:a@b.c"), Link::Ignored);
+		assert_eq!(Link::parse("#fn-1"), Link::Ignored);
+		assert_eq!(
+			Link::parse("/static/a%20b.png?v=1"),
+			Link::Internal("/static/a b.png".to_string())
+		);
+		assert_eq!(
+			Link::parse("../tag/rust.html#top"),
+			Link::Internal("../tag/rust.html".to_string())
+		);
+	}
+}
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+	error::Error,
+	page_builder::NavLink,
+	parsers::{error::ParsingError, toml},
+	Result,
+};
+
+/// Settings for the whole site, read from an optional `site.toml`. For now
+/// that's the links in the header and footer, which are the site's usual
+/// ones without it:
+///
+/// ```toml
+/// [[nav]]
+/// label = "bsky"
+/// href = "https://bsky.app/profile/mcpar.land"
+/// external = true # open in a new tab
+/// icon = "/static/bsky.png" # optional
+///
+/// [[footer]]
+/// label = "Download Site"
+/// href = "/site.zip"
+/// ```
+pub struct SiteConfig {
+	pub nav: Vec<NavLink>,
+	pub footer: Vec<NavLink>,
+}
+
+impl Default for SiteConfig {
+	fn default() -> Self {
+		let link = |label: &str, href: &str, external: bool| NavLink {
+			label: label.to_string(),
+			href: href.to_string(),
+			external,
+			icon: None,
+		};
+		Self {
+			nav: vec![
+				link("blog", "/blog.html", false),
+				link("rss", "/feed.xml", false),
+				link("bsky", "https://bsky.app/profile/mcpar.land", true),
+				link("github", "https://github.com/mcpar-land", true),
+			],
+			footer: vec![
+				link("Download Site", "/site.zip", false),
+				link(
+					"Repo",
+					"https://github.com/mcpar-land/mcpar-land.github.io",
+					false,
+				),
+			],
+		}
+	}
+}
+
+impl SiteConfig {
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+		let path = path.as_ref();
+		if !path.is_file() {
+			return Ok(Self::default());
+		}
+		let err = |error| Error::Parsing {
+			path: PathBuf::from(path),
+			error,
+		};
+
+		let table = toml::parse(&std::fs::read_to_string(path)?).map_err(err)?;
+
+		// Anything left out of the file is left out of the site.
+		let mut config = Self {
+			nav: Vec::new(),
+			footer: Vec::new(),
+		};
+		for (key, value) in &table {
+			let links = match key.as_str() {
+				"nav" => &mut config.nav,
+				"footer" => &mut config.footer,
+				key => {
+					return Err(err(ParsingError::InvalidSiteConfig(format!(
+						"unknown key {}",
+						key
+					))))
+				}
+			};
+			*links = parse_links(key, value)
+				.map_err(|reason| err(ParsingError::InvalidSiteConfig(reason)))?;
+		}
+		Ok(config)
+	}
+}
+
+fn parse_links(
+	key: &str,
+	value: &toml::Value,
+) -> std::result::Result<Vec<NavLink>, String> {
+	let tables = value
+		.as_array()
+		.ok_or_else(|| format!("{} should be an array of tables", key))?;
+
+	let mut links = Vec::new();
+	for (i, table) in tables.iter().enumerate() {
+		let table = table
+			.as_table()
+			.ok_or_else(|| format!("{} should be an array of tables", key))?;
+		let string = |name: &str| match table.get(name) {
+			None => Ok(None),
+			Some(value) => value
+				.as_str()
+				.map(|s| Some(s.to_string()))
+				.ok_or_else(|| format!("{}[{}].{} should be a string", key, i, name)),
+		};
+		if let Some(unknown) = table
+			.keys()
+			.find(|k| !["label", "href", "external", "icon"].contains(&k.as_str()))
+		{
+			return Err(format!("unknown key {}[{}].{}", key, i, unknown));
+		}
+
+		links.push(NavLink {
+			label: string("label")?
+				.ok_or_else(|| format!("{}[{}] needs a label", key, i))?,
+			href: string("href")?
+				.ok_or_else(|| format!("{}[{}] needs an href", key, i))?,
+			external: match table.get("external") {
+				None => false,
+				Some(value) => value.as_bool().ok_or_else(|| {
+					format!("{}[{}].external should be true or false", key, i)
+				})?,
+			},
+			icon: string("icon")?,
+		});
+	}
+	Ok(links)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_load() {
+		let missing = SiteConfig::load("./no-such-site.toml").unwrap();
+		assert_eq!(missing.nav.len(), 4);
+		assert_eq!(missing.footer[0].href, "/site.zip");
+		// The site's own site.toml has the same links.
+		let site = SiteConfig::load("./site.toml").unwrap();
+		let hrefs = |links: &[NavLink]| {
+			links
+				.iter()
+				.map(|link| link.href.clone())
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(hrefs(&site.nav), hrefs(&missing.nav));
+		assert_eq!(hrefs(&site.footer), hrefs(&missing.footer));
+	}
+
+	#[test]
+	fn test_parse_links() {
+		let table = toml::parse(
+			r#"
+[[nav]]
+label = "blog"
+href = "/blog.html"
+
+[[nav]]
+label = "bsky"
+href = "https://bsky.app"
+external = true
+icon = "/static/bsky.png"
+
+[[footer]]
+label = "no href"
+"#,
+		)
+		.unwrap();
+
+		let nav = parse_links("nav", &table["nav"]).unwrap();
+		assert_eq!(nav.len(), 2);
+		assert!(!nav[0].external);
+		assert!(nav[1].external);
+		assert_eq!(nav[1].icon.as_deref(), Some("/static/bsky.png"));
+		assert_eq!(
+			parse_links("footer", &table["footer"]).err().as_deref(),
+			Some("footer[0] needs an href")
+		);
+	}
+}
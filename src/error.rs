@@ -1,13 +1,19 @@
 use std::path::PathBuf;
 
-use crate::parsers::error::ParsingError;
+use crate::{
+	check::BrokenLink, markdown::links::UnresolvedLink,
+	parsers::error::ParsingError,
+};
 
 #[derive(Debug)]
 pub enum Error {
 	Parsing { path: PathBuf, error: ParsingError },
 	FileNotFound(PathBuf),
 	InvalidPostFile { path: PathBuf, reason: String },
+	UnresolvedLinks(Vec<(PathBuf, UnresolvedLink)>),
+	BrokenLinks(Vec<BrokenLink>),
 	NoChildrenNoTemplate,
+	InvalidArgument(String),
 	Fs(std::io::Error),
 	Zip(zip::result::ZipError),
 }
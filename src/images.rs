@@ -0,0 +1,368 @@
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use std::{
+	cell::RefCell,
+	collections::HashSet,
+	fs,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	util::{files_in, fnv1a, url_to_static_path},
+	Result,
+};
+
+/// Images with these extensions get resized copies. GIFs are left alone so
+/// they keep their animation.
+const RESIZABLE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+#[derive(Clone)]
+pub struct ImageOptions {
+	/// Widths to make resized copies of each image at. Only widths smaller than
+	/// the original are used.
+	pub widths: Vec<u32>,
+	/// The `sizes` attribute for images with resized copies.
+	pub sizes: String,
+	/// Also make a WebP copy of every size.
+	pub webp: bool,
+}
+
+impl Default for ImageOptions {
+	fn default() -> Self {
+		Self {
+			widths: vec![300, 600, 1200],
+			sizes: "(min-width: 600px) 600px, 100vw".to_string(),
+			webp: false,
+		}
+	}
+}
+
+/// Makes resized copies of images in `./static`, written to `/images/` in the
+/// output. Copies are cached in `./.cache/images` by a hash of the original
+/// file, so an image is only resized again when it changes.
+pub struct ResponsiveImages {
+	options: ImageOptions,
+	cache_dir: PathBuf,
+	output_dir: PathBuf,
+	/// Names of the copies in `output_dir` this build has used.
+	written: RefCell<HashSet<String>>,
+}
+
+pub struct Variant {
+	pub href: String,
+	pub width: u32,
+}
+
+/// Everything needed to write an image's `srcset`.
+pub struct Srcset<'a> {
+	pub width: u32,
+	pub height: u32,
+	pub sizes: &'a str,
+	/// Resized copies in the original format, smallest first, followed by the
+	/// original itself.
+	pub variants: Vec<Variant>,
+	/// The same sizes as WebP, if that's turned on.
+	pub webp: Vec<Variant>,
+}
+
+impl Srcset<'_> {
+	pub fn srcset(&self) -> String {
+		srcset(&self.variants)
+	}
+
+	pub fn webp_srcset(&self) -> Option<String> {
+		(!self.webp.is_empty()).then(|| srcset(&self.webp))
+	}
+}
+
+fn srcset(variants: &[Variant]) -> String {
+	variants
+		.iter()
+		// A space would end the url early.
+		.map(|variant| {
+			format!("{} {}w", variant.href.replace(' ', "%20"), variant.width)
+		})
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+impl ResponsiveImages {
+	pub fn new(options: ImageOptions) -> Self {
+		Self {
+			options,
+			cache_dir: PathBuf::from("./.cache/images"),
+			output_dir: PathBuf::from("./output/images"),
+			written: RefCell::default(),
+		}
+	}
+
+	/// Remove copies from earlier builds that this one didn't use, like ones
+	/// at widths that aren't asked for any more or of deleted images. Only
+	/// call this once everything has been rendered.
+	pub fn remove_unused(&self) -> Result<()> {
+		if !self.output_dir.is_dir() {
+			return Ok(());
+		}
+		let written = self.written.borrow();
+		for file in files_in(&self.output_dir)? {
+			let name = file.strip_prefix(&self.output_dir).unwrap_or(&file);
+			if !written.contains(name.to_string_lossy().as_ref()) {
+				fs::remove_file(file)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Resize the image at `url`, returning `None` if it isn't a local image
+	/// that can be resized. Problems reading the image are printed as warnings
+	/// rather than stopping the build, the image just goes without a `srcset`.
+	pub fn srcset(&self, url: &str) -> Option<Srcset<'_>> {
+		let path = url_to_static_path(url)?;
+		let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+		if !RESIZABLE_EXTENSIONS.contains(&extension.as_str()) {
+			return None;
+		}
+
+		match self.resize(url, &path, &extension) {
+			Ok(srcset) => Some(srcset),
+			Err(err) => {
+				println!("⚠️  Couldn't resize {}: {}", path.display(), err);
+				None
+			}
+		}
+	}
+
+	fn resize(
+		&self,
+		url: &str,
+		path: &Path,
+		extension: &str,
+	) -> std::result::Result<Srcset<'_>, Box<dyn std::error::Error>> {
+		let bytes = fs::read(path)?;
+		let hash = format!("{:016x}", fnv1a(&bytes));
+		let (width, height) = image::ImageReader::new(std::io::Cursor::new(&bytes))
+			.with_guessed_format()?
+			.into_dimensions()?;
+		let stem = path
+			.file_stem()
+			.unwrap_or_default()
+			.to_string_lossy()
+			.replace(
+				|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '_',
+				"-",
+			);
+
+		fs::create_dir_all(&self.cache_dir)?;
+		fs::create_dir_all(&self.output_dir)?;
+
+		// Only decoded if a copy isn't in the cache already.
+		let mut image: Option<DynamicImage> = None;
+		let mut variant = |resized_width: u32, extension: &str| {
+			let name = format!("{}-{}w.{}", hash, resized_width, extension);
+			let cached = self.cache_dir.join(&name);
+			if !cached.is_file() {
+				let image = match &mut image {
+					Some(image) => image,
+					None => image.insert(image::load_from_memory(&bytes)?),
+				};
+				save(image, resized_width, &cached)?;
+			}
+
+			let output_name =
+				format!("{}-{}-{}w.{}", stem, &hash[..8], resized_width, extension);
+			let output = self.output_dir.join(&output_name);
+			if !output.is_file() {
+				fs::copy(&cached, &output)?;
+			}
+			self.written.borrow_mut().insert(output_name.clone());
+			Ok::<_, Box<dyn std::error::Error>>(Variant {
+				href: format!("/images/{}", output_name),
+				width: resized_width,
+			})
+		};
+
+		let widths = self
+			.options
+			.widths
+			.iter()
+			.copied()
+			.filter(|w| *w < width)
+			.collect::<Vec<_>>();
+
+		let mut variants = Vec::new();
+		for w in &widths {
+			variants.push(variant(*w, extension)?);
+		}
+		variants.push(Variant {
+			href: url.to_string(),
+			width,
+		});
+
+		let mut webp = Vec::new();
+		if self.options.webp {
+			for w in widths.iter().copied().chain([width]) {
+				webp.push(variant(w, "webp")?);
+			}
+		}
+
+		Ok(Srcset {
+			width,
+			height,
+			sizes: &self.options.sizes,
+			variants,
+			webp,
+		})
+	}
+}
+
+fn save(
+	image: &DynamicImage,
+	width: u32,
+	path: &Path,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+	let resized = if width < image.width() {
+		image.resize(width, u32::MAX, FilterType::Lanczos3)
+	} else {
+		image.clone()
+	};
+	let format = ImageFormat::from_path(path)?;
+	// JPEG has no transparency, and WebP can only be written as 8 bit.
+	let resized = match format {
+		ImageFormat::Jpeg => DynamicImage::ImageRgb8(resized.to_rgb8()),
+		ImageFormat::WebP => DynamicImage::ImageRgba8(resized.to_rgba8()),
+		_ => resized,
+	};
+	resized.save_with_format(path, format)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn temp_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!(
+			"images-{}-{}",
+			name,
+			std::process::id()
+		));
+		if dir.exists() {
+			fs::remove_dir_all(&dir).unwrap();
+		}
+		fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	fn images(dir: &Path, options: ImageOptions) -> ResponsiveImages {
+		ResponsiveImages {
+			options,
+			cache_dir: dir.join("cache"),
+			output_dir: dir.join("output"),
+			written: RefCell::default(),
+		}
+	}
+
+	#[test]
+	fn test_srcset() {
+		let dir = temp_dir("srcset");
+		let source = dir.join("wide.png");
+		DynamicImage::new_rgb8(800, 400).save(&source).unwrap();
+
+		let images = images(
+			&dir,
+			ImageOptions {
+				widths: vec![200, 400, 1600],
+				webp: true,
+				..Default::default()
+			},
+		);
+		let srcset = images.resize("/static/wide.png", &source, "png").unwrap();
+
+		assert_eq!((srcset.width, srcset.height), (800, 400));
+		assert_eq!(
+			srcset
+				.variants
+				.iter()
+				.map(|variant| variant.width)
+				.collect::<Vec<_>>(),
+			[200, 400, 800]
+		);
+		assert_eq!(srcset.variants[2].href, "/static/wide.png");
+		assert!(srcset.variants[0].href.starts_with("/images/wide-"));
+		assert!(srcset.variants[0].href.ends_with("-200w.png"));
+		assert_eq!(srcset.webp.len(), 3);
+		assert!(srcset.webp[2].href.ends_with("-800w.webp"));
+
+		let resized = image::open(
+			dir
+				.join("output")
+				.join(srcset.variants[0].href.strip_prefix("/images/").unwrap()),
+		)
+		.unwrap();
+		assert_eq!((resized.width(), resized.height()), (200, 100));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn test_remove_unused() {
+		let dir = temp_dir("unused");
+		let source = dir.join("wide.png");
+		DynamicImage::new_rgb8(800, 400).save(&source).unwrap();
+		let widths = |widths: Vec<u32>| {
+			images(
+				&dir,
+				ImageOptions {
+					widths,
+					..Default::default()
+				},
+			)
+		};
+		let names = || {
+			files_in(dir.join("output"))
+				.unwrap()
+				.iter()
+				.map(|file| file.file_name().unwrap().to_string_lossy().to_string())
+				.collect::<Vec<_>>()
+		};
+
+		widths(vec![200, 400])
+			.resize("/static/wide.png", &source, "png")
+			.unwrap();
+		fs::write(dir.join("output/deleted-01234567-200w.png"), "").unwrap();
+		assert_eq!(names().len(), 3);
+
+		// A build with different widths only keeps the copies it used.
+		let images = widths(vec![200]);
+		let srcset = images.resize("/static/wide.png", &source, "png").unwrap();
+		images.remove_unused().unwrap();
+		assert_eq!(
+			names(),
+			[srcset.variants[0].href.strip_prefix("/images/").unwrap()]
+		);
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn test_srcset_attribute() {
+		let srcset = Srcset {
+			width: 1000,
+			height: 500,
+			sizes: "100vw",
+			variants: vec![
+				Variant {
+					href: "/images/a-0123abcd-500w.png".to_string(),
+					width: 500,
+				},
+				Variant {
+					href: "/static/a b.png".to_string(),
+					width: 1000,
+				},
+			],
+			webp: Vec::new(),
+		};
+		assert_eq!(
+			srcset.srcset(),
+			"/images/a-0123abcd-500w.png 500w, /static/a%20b.png 1000w"
+		);
+		assert_eq!(srcset.webp_srcset(), None);
+	}
+}
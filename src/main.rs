@@ -1,26 +1,198 @@
 use crate::{
-	blog::blog_list, page_builder::PageBuilder, post::read_all_posts,
-	rss::rss_feed, tags::gen_tag_pages, util::Siblings,
+	assets::AssetManifest,
+	blog::{blog_list, gen_archive_pages},
+	config::SiteConfig,
+	images::ImageOptions,
+	page_builder::PageBuilder,
+	pages::{gen_pages, nav_links, read_all_pages},
+	pagination::paginate,
+	post::{read_post_files, render_all_posts, MarkdownRenderer},
+	related::{related_posts, RELATED_POSTS},
+	rss::rss_feed,
+	series::{gen_series_pages, Series},
+	styles::Stylesheet,
+	tags::{gen_tag_pages, TagConfig, TagOrder},
+	templates::{list_vars, post_vars, Layout, Templates},
+	util::Siblings,
 };
+use error::Error;
 use maud::{html, Markup};
 use post::Post;
-use std::path::PathBuf;
+use std::{path::PathBuf, rc::Rc};
 
+pub mod assets;
 pub mod blog;
+pub mod check;
+pub mod config;
 pub mod error;
+pub mod images;
+pub mod markdown {
+	pub mod admonitions;
+	pub mod callout;
+	pub mod excerpt;
+	pub mod footnotes;
+	pub mod links;
+	pub mod math;
+	pub mod media;
+	pub mod shortcodes;
+	pub mod spans;
+}
 pub mod page_builder;
+pub mod pages;
+pub mod pagination;
 pub mod post;
+pub mod related;
 pub mod rss;
+pub mod series;
+pub mod styles;
 pub mod tags;
+pub mod templates;
 pub mod util;
 pub mod parsers {
 	pub mod combinators;
 	pub mod date;
 	pub mod error;
 	pub mod frontmatter;
+	pub mod latex;
+	pub mod toml;
+}
+
+const DEFAULT_MAX_ASSET_KB: u64 = 1024;
+const DEFAULT_PAGE_SIZE: usize = 0;
+const DEFAULT_HOMEPAGE_POSTS: usize = 3;
+
+/// Run with `check` to look for broken links once the site is built, and
+/// `--external` to also list every link to another site. `--prune` leaves
+/// static files no page uses out of the output, and `--max-asset-kb=<size>`
+/// sets the size images and videos are warned about over. Images are resized
+/// to each of `--image-widths=<w>,<w>,...`, and also converted to WebP with
+/// `--webp`. `--tag-order=count` lists tags by how many posts they have
+/// rather than alphabetically. `--page-size=<n>` splits the blog and tag
+/// lists into pages of that many posts, which they aren't by default, and
+/// `--homepage-posts=<n>` sets how many are on the homepage. `--external-css`
+/// links every page to one minified copy of the stylesheet rather than
+/// inlining it. `--fingerprint` copies static files to names with a hash of
+/// their contents, points pages at those, and lists them in
+/// `asset-manifest.json`.
+struct Args {
+	check: bool,
+	external: bool,
+	prune: bool,
+	max_asset_size: u64,
+	images: ImageOptions,
+	tag_order: TagOrder,
+	page_size: usize,
+	homepage_posts: usize,
+	external_css: bool,
+	fingerprint: bool,
+}
+
+/// Arguments that are on or off.
+const FLAGS: [&str; 6] = [
+	"check",
+	"--external",
+	"--prune",
+	"--webp",
+	"--external-css",
+	"--fingerprint",
+];
+
+/// Arguments that take a value, like `--page-size=5`.
+const OPTIONS: [&str; 5] = [
+	"--max-asset-kb",
+	"--image-widths",
+	"--tag-order",
+	"--page-size",
+	"--homepage-posts",
+];
+
+impl Args {
+	/// Anything that isn't a known argument, or doesn't have a value that
+	/// parses, is an error rather than falling back to the default.
+	fn parse(args: &[String]) -> Result<Self> {
+		for arg in args {
+			let name = arg.split_once('=').map_or(arg.as_str(), |(name, _)| name);
+			let known = if arg.contains('=') {
+				OPTIONS.contains(&name)
+			} else {
+				FLAGS.contains(&name)
+			};
+			if !known {
+				let reason = if OPTIONS.contains(&name) {
+					format!("{} needs a value, like {}=<value>", name, name)
+				} else {
+					format!("unknown argument {}", arg)
+				};
+				return Err(Error::InvalidArgument(reason));
+			}
+		}
+
+		let has = |arg: &str| args.iter().any(|a| a == arg);
+		let value = |name: &str| {
+			args
+				.iter()
+				.find_map(|a| a.strip_prefix(name)?.strip_prefix('='))
+		};
+		fn parsed<T: std::str::FromStr>(name: &str, value: &str) -> Result<T> {
+			value.trim().parse().map_err(|_| {
+				Error::InvalidArgument(format!("{}={} isn't valid", name, value))
+			})
+		}
+
+		Ok(Self {
+			check: has("check"),
+			external: has("--external"),
+			prune: has("--prune"),
+			max_asset_size: value("--max-asset-kb")
+				.map_or(Ok(DEFAULT_MAX_ASSET_KB), |kb| parsed("--max-asset-kb", kb))?
+				* 1024,
+			images: ImageOptions {
+				widths: match value("--image-widths") {
+					Some(widths) => widths
+						.split(',')
+						.filter(|w| !w.trim().is_empty())
+						.map(|w| parsed("--image-widths", w))
+						.collect::<Result<_>>()?,
+					None => ImageOptions::default().widths,
+				},
+				webp: has("--webp"),
+				..Default::default()
+			},
+			tag_order: match value("--tag-order") {
+				Some(order) => TagOrder::parse(order).ok_or_else(|| {
+					Error::InvalidArgument(format!(
+						"--tag-order={} should be alphabetical or count",
+						order
+					))
+				})?,
+				None => TagOrder::default(),
+			},
+			page_size: value("--page-size")
+				.map_or(Ok(DEFAULT_PAGE_SIZE), |size| parsed("--page-size", size))?,
+			homepage_posts: value("--homepage-posts")
+				.map_or(Ok(DEFAULT_HOMEPAGE_POSTS), |count| {
+					parsed("--homepage-posts", count)
+				})?,
+			external_css: has("--external-css"),
+			fingerprint: has("--fingerprint"),
+		})
+	}
 }
 
 fn main() -> Result<()> {
+	let args = Args::parse(&std::env::args().skip(1).collect::<Vec<_>>())?;
+
+	build(&args)?;
+
+	if args.check {
+		println!("🔍 Checking links");
+		check::check("./output", args.external)?;
+	}
+
+	Ok(())
+}
+
+fn build(args: &Args) -> Result<()> {
 	// println!("🗑️  Deleting output directory");
 
 	// if std::path::Path::new("./output").is_dir() {
@@ -33,65 +205,136 @@ fn main() -> Result<()> {
 
 	println!("🗃️  Generating webpages.");
 
-	let mut all_posts = read_all_posts()?;
+	let site_config = SiteConfig::load("./site.toml")?;
+	let tag_config = TagConfig::load("./tags.toml")?;
+	let templates = Templates::load("./templates")?;
+	let assets = if args.fingerprint {
+		AssetManifest::load("./static")?
+	} else {
+		AssetManifest::default()
+	};
+	let stylesheet = Rc::new(Stylesheet::load(
+		"./styles.css",
+		args.external_css,
+		&assets,
+	)?);
+	let post_files = read_post_files()?;
+	let renderer = MarkdownRenderer::new(&args.images, &post_files);
+	let mut all_posts = render_all_posts(post_files, &renderer, &tag_config)?;
+	templates.check_layouts(&all_posts)?;
+	let pages = read_all_pages("./pages", &renderer)?;
+	renderer.remove_unused_images()?;
 
 	let builder = PageBuilder::new()
 		.title("john mcparland")
-		.description("Hello!");
+		.description("Hello!")
+		.nav(nav_links(&pages, site_config.nav))
+		.footer(site_config.footer)
+		.templates(Rc::new(templates))
+		.stylesheet(stylesheet.clone());
 
 	// Write all pages
 	builder
 		.clone()
-		.body(homepage(&all_posts)?)
+		.body(homepage(&all_posts, args.homepage_posts)?)
 		.write("index.html")?;
-	builder
-		.clone()
-		.body(blog_list(&all_posts)?)
-		.write("blog.html")?;
+	for page in paginate(&all_posts, args.page_size, "/blog") {
+		builder
+			.clone()
+			.head(page.head())
+			.layout(Layout::List, list_vars(&page))
+			.body(blog_list(&page)?)
+			.write(page.path())?;
+	}
+	gen_archive_pages(&builder, &all_posts)?;
 	builder
 		.clone()
 		.body(rss_feed(&all_posts)?)
 		.no_template()
 		.write("feed.xml")?;
-	gen_tag_pages(&builder, &all_posts)?;
-	builder.clone().body(page404()?).write("404.html")?;
+	gen_tag_pages(
+		&builder,
+		&all_posts,
+		&tag_config,
+		args.tag_order,
+		args.page_size,
+	)?;
+	builder
+		.clone()
+		.inline_css()
+		.body(page404()?)
+		.write("404.html")?;
+	gen_pages(&builder, &pages)?;
 
 	all_posts.reverse();
 
+	let series = Series::collect(&all_posts);
+	gen_series_pages(&builder, &series)?;
+
+	let related = related_posts(&all_posts, RELATED_POSTS);
+
 	// Write all posts
-	for (prev, post, next) in Siblings::new(&all_posts) {
-		PageBuilder::new()
+	for (i, (prev, post, next)) in Siblings::new(&all_posts).enumerate() {
+		let post_series = series.iter().find(|s| s.part(post).is_some());
+		builder
+			.clone()
 			.title(&format!("{} - john mcparland", &post.frontmatter.title))
-			.description(&post.frontmatter.description)
-			.body(post.render(prev, next))
-			.write(&format!("posts/{}.html", &post.filename))?;
+			.description(post.summary())
+			.head(post.head.clone())
+			.layout(post.layout(), post_vars(post))
+			.body(post.render(prev, next, post_series, &related[i]))
+			.write(format!("posts/{}.html", &post.filename))?;
 	}
 
 	println!("💾 Copying static assets");
-	crate::util::copy_dir("./static", "./output/static")?;
+	assets::copy_static(
+		"./static",
+		"./output",
+		"./styles.css",
+		args.prune,
+		args.max_asset_size,
+	)?;
+	stylesheet.write("./output")?;
+	assets.write("./static", "./output")?;
 
 	println!("🗃️ Creating site archive");
 	if PathBuf::from("./output/site.zip").is_file() {
 		std::fs::remove_file("./output/site.zip")?;
 	}
-	zip_extensions::zip_create_from_directory(
-		&PathBuf::from("./site.zip"),
-		&PathBuf::from("./output"),
-	)?;
+	zip_site("./site.zip")?;
 	std::fs::rename("./site.zip", "./output/site.zip")?;
 
 	println!("✨ Finish!");
 	Ok(())
 }
 
-fn homepage(all_posts: &Vec<Post>) -> Result<Markup> {
+/// Zip up `./output`, in sorted order and without timestamps so that an
+/// unchanged site gives a byte-identical archive.
+fn zip_site(to: &str) -> Result<()> {
+	use std::io::Write;
+	use zip::write::SimpleFileOptions;
+
+	let options = SimpleFileOptions::default()
+		.compression_method(zip::CompressionMethod::Deflated)
+		.last_modified_time(zip::DateTime::default());
+	let mut zip = zip::ZipWriter::new(std::fs::File::create(to)?);
+	for file in util::files_in("./output")? {
+		let name = file.strip_prefix("./output").unwrap_or(&file);
+		zip.start_file(name.to_string_lossy(), options)?;
+		zip.write_all(&std::fs::read(&file)?)?;
+	}
+	zip.finish()?;
+	Ok(())
+}
+
+fn homepage(all_posts: &[Post], count: usize) -> Result<Markup> {
 	Ok(html! {
 		p {
 			"Hello! I'm a developer located in Boston, and this is my website. "
 			"Take a look around."
 		}
 		.post-list {
-			@for post in all_posts.iter().take(3) {
+			@for post in all_posts.iter().take(count) {
 				(post.as_list_item())
 			}
 			a href="/blog.html" style="text-align:right;" {
@@ -118,3 +361,51 @@ fn page404() -> Result<Markup> {
 }
 
 pub type Result<T> = std::result::Result<T, crate::error::Error>;
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn parse(args: &[&str]) -> Result<Args> {
+		Args::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+	}
+
+	#[test]
+	fn test_args() {
+		let args = parse(&[
+			"check",
+			"--prune",
+			"--page-size=5",
+			"--image-widths=300, 600",
+			"--tag-order=count",
+			"--max-asset-kb=2",
+		])
+		.unwrap();
+		assert!(args.check && args.prune && !args.fingerprint);
+		assert_eq!(args.page_size, 5);
+		assert_eq!(args.images.widths, [300, 600]);
+		assert_eq!(args.tag_order, TagOrder::Count);
+		assert_eq!(args.max_asset_size, 2048);
+
+		let args = parse(&[]).unwrap();
+		assert_eq!(args.page_size, DEFAULT_PAGE_SIZE);
+		assert_eq!(args.homepage_posts, DEFAULT_HOMEPAGE_POSTS);
+
+		for bad in [
+			"--fingerprnt",
+			"--prune=yes",
+			"--page-size",
+			"--page-size=abc",
+			"--homepage-posts=-1",
+			"--tag-order=bogus",
+			"--image-widths=300,big",
+			"build",
+		] {
+			assert!(
+				matches!(parse(&[bad]), Err(Error::InvalidArgument(_))),
+				"{}",
+				bad
+			);
+		}
+	}
+}
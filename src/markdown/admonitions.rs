@@ -0,0 +1,150 @@
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::collections::VecDeque;
+
+use crate::markdown::callout::{callout_start, CalloutKind, CALLOUT_END};
+
+/// Turn GitHub style admonitions into callouts:
+///
+/// ```markdown
+/// > [!WARNING]
+/// > Anything in here is still **markdown**.
+/// ```
+///
+/// Blockquotes that don't start with a `[!KIND]` marker on its own line are
+/// passed through untouched.
+pub fn admonitions<'a, I: Iterator<Item = Event<'a>>>(
+	iter: I,
+) -> Admonitions<'a, I> {
+	Admonitions {
+		iter,
+		pending: VecDeque::new(),
+		blockquotes: Vec::new(),
+	}
+}
+
+pub struct Admonitions<'a, I: Iterator<Item = Event<'a>>> {
+	iter: I,
+	/// Events that were read ahead while looking for a marker.
+	pending: VecDeque<Event<'a>>,
+	/// Whether each currently open blockquote was turned into a callout.
+	blockquotes: Vec<bool>,
+}
+
+impl<'a, I: Iterator<Item = Event<'a>>> Admonitions<'a, I> {
+	fn next_raw(&mut self) -> Option<Event<'a>> {
+		self.pending.pop_front().or_else(|| self.iter.next())
+	}
+
+	/// Called just after a blockquote starts. Reads ahead through the first
+	/// line of its first paragraph, and if that's a marker, consumes it and
+	/// returns the kind of callout. Otherwise everything read is put back.
+	fn read_marker(&mut self) -> Option<CalloutKind> {
+		let mut read = Vec::new();
+
+		match self.next_raw() {
+			Some(event @ Event::Start(Tag::Paragraph)) => read.push(event),
+			Some(event) => {
+				self.pending.push_front(event);
+				return None;
+			}
+			None => return None,
+		}
+
+		let mut marker = String::new();
+		let end = loop {
+			match self.next_raw() {
+				Some(Event::Text(text)) => {
+					marker.push_str(&text);
+					read.push(Event::Text(text));
+				}
+				end => break end,
+			}
+		};
+
+		let kind = marker
+			.trim()
+			.strip_prefix("[!")
+			.and_then(|marker| marker.strip_suffix(']'))
+			.and_then(CalloutKind::parse);
+
+		match (kind, end) {
+			// The marker was followed by more text, so the paragraph goes on.
+			(Some(kind), Some(Event::SoftBreak | Event::HardBreak)) => {
+				self.pending.push_front(Event::Start(Tag::Paragraph));
+				Some(kind)
+			}
+			// The marker was a paragraph of its own, drop the whole thing.
+			(Some(kind), Some(Event::End(Tag::Paragraph))) => Some(kind),
+			(_, end) => {
+				if let Some(end) = end {
+					self.pending.push_front(end);
+				}
+				for event in read.into_iter().rev() {
+					self.pending.push_front(event);
+				}
+				None
+			}
+		}
+	}
+}
+
+impl<'a, I: Iterator<Item = Event<'a>>> Iterator for Admonitions<'a, I> {
+	type Item = Event<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		match self.next_raw()? {
+			Event::Start(Tag::BlockQuote) => match self.read_marker() {
+				Some(kind) => {
+					self.blockquotes.push(true);
+					Some(Event::Html(CowStr::from(callout_start(kind, None))))
+				}
+				None => {
+					self.blockquotes.push(false);
+					Some(Event::Start(Tag::BlockQuote))
+				}
+			},
+			Event::End(Tag::BlockQuote) => match self.blockquotes.pop() {
+				Some(true) => Some(Event::Html(CowStr::from(CALLOUT_END))),
+				_ => Some(Event::End(Tag::BlockQuote)),
+			},
+			event => Some(event),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::post::markdown_options;
+
+	fn render(input: &str) -> String {
+		let parser = pulldown_cmark::Parser::new_ext(input, markdown_options());
+		let mut output = String::new();
+		pulldown_cmark::html::push_html(&mut output, admonitions(parser));
+		output
+	}
+
+	#[test]
+	fn test_admonition() {
+		let output = render("> [!TIP]\n> Some *emphasis*.\n>\n> > nested quote");
+		assert!(output.starts_with("<aside class=\"callout callout-tip\">"));
+		assert!(output.contains("<p>Some <em>emphasis</em>.</p>"));
+		assert!(output.contains("<blockquote>\n<p>nested quote</p>\n</blockquote>"));
+		assert!(output.trim_end().ends_with("</aside>"));
+		assert!(!output.contains("[!TIP]"));
+	}
+
+	#[test]
+	fn test_marker_paragraph() {
+		let output = render("> [!note]\n>\n> Body");
+		assert!(output.starts_with("<aside class=\"callout callout-note\">"));
+		assert!(output.contains("<p>Body</p>"));
+	}
+
+	#[test]
+	fn test_plain_blockquote() {
+		let output = render("> [!NOPE]\n> just a quote");
+		assert!(output.starts_with("<blockquote>"));
+		assert!(output.contains("[!NOPE]"));
+	}
+}
@@ -0,0 +1,67 @@
+use maud::html;
+
+#[derive(Clone, Copy)]
+pub enum CalloutKind {
+	Note,
+	Tip,
+	Important,
+	Warning,
+	Caution,
+}
+
+impl CalloutKind {
+	pub fn parse(kind: &str) -> Option<Self> {
+		match kind.to_ascii_lowercase().as_str() {
+			"note" => Some(Self::Note),
+			"tip" => Some(Self::Tip),
+			"important" => Some(Self::Important),
+			"warning" => Some(Self::Warning),
+			"caution" => Some(Self::Caution),
+			_ => None,
+		}
+	}
+
+	pub fn class(&self) -> &'static str {
+		match self {
+			Self::Note => "callout-note",
+			Self::Tip => "callout-tip",
+			Self::Important => "callout-important",
+			Self::Warning => "callout-warning",
+			Self::Caution => "callout-caution",
+		}
+	}
+
+	pub fn title(&self) -> &'static str {
+		match self {
+			Self::Note => "Note",
+			Self::Tip => "Tip",
+			Self::Important => "Important",
+			Self::Warning => "Warning",
+			Self::Caution => "Caution",
+		}
+	}
+
+	pub fn icon(&self) -> &'static str {
+		match self {
+			Self::Note => "ℹ",
+			Self::Tip => "✦",
+			Self::Important => "❢",
+			Self::Warning => "⚠",
+			Self::Caution => "⛔",
+		}
+	}
+}
+
+/// Opening half of a callout. The contents go between this and
+/// [`CALLOUT_END`], so they can be markdown or already rendered html.
+pub fn callout_start(kind: CalloutKind, title: Option<&str>) -> String {
+	let header = html! {
+		p.callout-title {
+			span.callout-icon aria-hidden="true" { (kind.icon()) }
+			(title.unwrap_or(kind.title()))
+		}
+	};
+	format!("<aside class=\"callout {}\">{}", kind.class(), header.0)
+}
+
+pub const CALLOUT_END: &str = "</aside>";
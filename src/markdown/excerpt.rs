@@ -0,0 +1,109 @@
+use pulldown_cmark::{Event, Tag};
+use std::ops::Range;
+
+/// The marker ending a post's excerpt, on a line of its own.
+const MORE: &str = "<!-- more -->";
+
+/// A plain text summary of a post: the text of every paragraph before a
+/// `<!-- more -->` marker, or without one, of the first paragraphs cut off at a
+/// word boundary once they're over `max_len` bytes.
+///
+/// Only text is kept, so any html, math or code in the paragraphs is dropped.
+pub fn excerpt(events: &[(Event, Range<usize>)], max_len: usize) -> String {
+	let has_marker = events.iter().any(|(event, _)| is_more(event));
+
+	let mut excerpt = String::new();
+	let mut in_paragraph = false;
+	let mut in_footnote = false;
+
+	for (event, _) in events {
+		match event {
+			event if is_more(event) => break,
+			Event::Start(Tag::FootnoteDefinition(_)) => in_footnote = true,
+			Event::End(Tag::FootnoteDefinition(_)) => in_footnote = false,
+			Event::Start(Tag::Paragraph) if !in_footnote => {
+				if !excerpt.is_empty() {
+					excerpt.push(' ');
+				}
+				in_paragraph = true;
+			}
+			Event::End(Tag::Paragraph) => {
+				in_paragraph = false;
+				if !has_marker && excerpt.len() >= max_len {
+					break;
+				}
+			}
+			Event::Text(text) if in_paragraph && !in_footnote => {
+				excerpt.push_str(text)
+			}
+			Event::SoftBreak | Event::HardBreak if in_paragraph => excerpt.push(' '),
+			_ => {}
+		}
+	}
+
+	let excerpt = excerpt.split_whitespace().collect::<Vec<_>>().join(" ");
+	if has_marker {
+		excerpt
+	} else {
+		truncate(&excerpt, max_len)
+	}
+}
+
+fn is_more(event: &Event) -> bool {
+	matches!(event, Event::Html(html) if html.trim() == MORE)
+}
+
+/// Cut `text` down to at most `max_len` bytes plus an ellipsis, without
+/// splitting a word.
+fn truncate(text: &str, max_len: usize) -> String {
+	if text.len() <= max_len {
+		return text.to_string();
+	}
+	let mut end = max_len;
+	while !text.is_char_boundary(end) {
+		end -= 1;
+	}
+	// Back up to the last space if that landed in the middle of a word, unless
+	// it's all one word.
+	let cut = match text[..end].rfind(' ') {
+		Some(space) if !text[end..].starts_with(' ') => &text[..space],
+		_ => &text[..end],
+	};
+	let cut = cut.trim_end_matches(|c: char| !c.is_alphanumeric());
+	format!("{}…", cut)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::post::markdown_options;
+
+	fn excerpt_of(input: &str, max_len: usize) -> String {
+		let parser = pulldown_cmark::Parser::new_ext(input, markdown_options());
+		excerpt(&parser.into_offset_iter().collect::<Vec<_>>(), max_len)
+	}
+
+	#[test]
+	fn test_marker() {
+		let input = "# Heading\n\nOne *two*\nthree.\n\n<div>four</div>\n\nFive.\n\n<!-- more -->\n\nSix.";
+		assert_eq!(excerpt_of(input, 5), "One two three. Five.");
+	}
+
+	#[test]
+	fn test_first_paragraphs() {
+		let input = "First bit.\n\n```\ncode\n```\n\nSecond paragraph goes on for a while.\n\nThird.";
+		assert_eq!(
+			excerpt_of(input, 200),
+			"First bit. Second paragraph goes on for a while. Third."
+		);
+		assert_eq!(excerpt_of(input, 25), "First bit. Second…");
+		assert_eq!(excerpt_of(input, 10), "First bit.");
+	}
+
+	#[test]
+	fn test_truncate() {
+		assert_eq!(truncate("héllo wörld", 8), "héllo…");
+		assert_eq!(truncate("abcdefgh", 4), "abcd…");
+		assert_eq!(truncate("one, two", 5), "one…");
+	}
+}
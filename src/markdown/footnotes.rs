@@ -0,0 +1,233 @@
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::collections::HashMap;
+
+/// Gather footnote definitions into a numbered list at the end of the post,
+/// with links back up to each reference.
+///
+/// pulldown-cmark leaves definitions wherever they were written and numbers
+/// them in that order too. Here footnotes are numbered in the order they're
+/// first referenced instead.
+///
+/// With `sidenotes` on, a copy of each footnote is also placed just before
+/// the block that first references it, which the stylesheet floats into the
+/// margin on wide screens.
+pub fn footnotes<'a>(
+	events: impl Iterator<Item = Event<'a>>,
+	sidenotes: bool,
+) -> Vec<Event<'a>> {
+	let mut definitions: HashMap<CowStr<'a>, Vec<Event<'a>>> = HashMap::new();
+	let mut body = Vec::new();
+	let mut definition: Option<(CowStr<'a>, Vec<Event<'a>>)> = None;
+
+	for event in events {
+		match event {
+			Event::Start(Tag::FootnoteDefinition(label)) => {
+				definition = Some((label, Vec::new()));
+			}
+			Event::End(Tag::FootnoteDefinition(_)) => {
+				if let Some((label, events)) = definition.take() {
+					definitions.insert(label, events);
+				}
+			}
+			event => match &mut definition {
+				Some((_, events)) => events.push(event),
+				None => body.push(event),
+			},
+		}
+	}
+
+	if definitions.is_empty() {
+		return body;
+	}
+
+	let mut numbers = Numbers::default();
+	let mut output = Vec::with_capacity(body.len());
+	let mut sidenote_positions = Vec::new();
+	let mut depth = 0;
+	let mut block_start = 0;
+
+	for event in body {
+		match event {
+			Event::Start(tag) => {
+				if depth == 0 {
+					block_start = output.len();
+				}
+				depth += 1;
+				output.push(Event::Start(tag));
+			}
+			Event::End(tag) => {
+				depth -= 1;
+				output.push(Event::End(tag));
+			}
+			Event::FootnoteReference(label) if definitions.contains_key(&label) => {
+				let (reference, first) = numbers.reference(&label);
+				if sidenotes && first && depth > 0 {
+					sidenote_positions.push((block_start, label));
+				}
+				output.push(reference);
+			}
+			Event::FootnoteReference(label) => {
+				output.push(Event::Text(CowStr::from(format!("[^{}]", label))));
+			}
+			event => output.push(event),
+		}
+	}
+
+	// Go backwards so inserting doesn't move the blocks that are still to go.
+	for (position, label) in sidenote_positions.into_iter().rev() {
+		let number = numbers.number(&label);
+		let mut sidenote = vec![Event::Html(CowStr::from(format!(
+			"<aside class=\"sidenote\"><span class=\"sidenote-number\">{}</span>",
+			number
+		)))];
+		sidenote.extend(
+			definitions[&label]
+				.iter()
+				.filter(|event| !matches!(event, Event::FootnoteReference(_)))
+				.cloned(),
+		);
+		sidenote.push(Event::Html(CowStr::Borrowed("</aside>")));
+		output.splice(position..position, sidenote);
+	}
+
+	output.push(Event::Html(CowStr::Borrowed(
+		"<section class=\"footnotes\"><ol>",
+	)));
+
+	// Footnotes can reference other footnotes, so more might be numbered as
+	// this goes.
+	let mut i = 0;
+	while let Some(label) = numbers.order.get(i).cloned() {
+		i += 1;
+		let number = numbers.number(&label);
+		let mut content = Vec::new();
+		for event in definitions.get(&label).cloned().unwrap_or_default() {
+			match event {
+				Event::FootnoteReference(label) if definitions.contains_key(&label) => {
+					content.push(numbers.reference(&label).0);
+				}
+				event => content.push(event),
+			}
+		}
+
+		let backlinks = (1..=numbers.count(&label))
+			.map(|n| {
+				format!(
+					" <a class=\"footnote-backref\" href=\"#{}\" aria-label=\"Back to reference {}\">↩{}</a>",
+					reference_id(number, n),
+					number,
+					if n > 1 { format!("<sup>{}</sup>", n) } else { String::new() },
+				)
+			})
+			.collect::<String>();
+		let backlinks = Event::Html(CowStr::from(backlinks));
+
+		// Put the links at the end of the last paragraph if there is one.
+		match content.last() {
+			Some(Event::End(Tag::Paragraph)) => {
+				let end = content.len() - 1;
+				content.insert(end, backlinks);
+			}
+			_ => content.push(backlinks),
+		}
+
+		output.push(Event::Html(CowStr::from(format!(
+			"<li id=\"fn-{}\">",
+			number
+		))));
+		output.extend(content);
+		output.push(Event::Html(CowStr::Borrowed("</li>")));
+	}
+
+	output.push(Event::Html(CowStr::Borrowed("</ol></section>")));
+
+	output
+}
+
+#[derive(Default)]
+struct Numbers<'a> {
+	order: Vec<CowStr<'a>>,
+	numbers: HashMap<CowStr<'a>, usize>,
+	counts: HashMap<CowStr<'a>, usize>,
+}
+
+impl<'a> Numbers<'a> {
+	fn number(&self, label: &CowStr<'a>) -> usize {
+		self.numbers[label]
+	}
+
+	fn count(&self, label: &CowStr<'a>) -> usize {
+		self.counts.get(label).copied().unwrap_or(0)
+	}
+
+	/// Html for a reference to a footnote, and whether it's the first one.
+	fn reference(&mut self, label: &CowStr<'a>) -> (Event<'a>, bool) {
+		let first = !self.numbers.contains_key(label);
+		if first {
+			self.order.push(label.clone());
+			self.numbers.insert(label.clone(), self.order.len());
+		}
+		let number = self.numbers[label];
+		let count = self.counts.entry(label.clone()).or_default();
+		*count += 1;
+
+		let html = format!(
+			"<sup class=\"footnote-reference\" id=\"{}\"><a href=\"#fn-{}\">{}</a></sup>",
+			reference_id(number, *count),
+			number,
+			number
+		);
+		(Event::Html(CowStr::from(html)), first)
+	}
+}
+
+fn reference_id(number: usize, count: usize) -> String {
+	if count == 1 {
+		format!("fnref-{}", number)
+	} else {
+		format!("fnref-{}-{}", number, count)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::post::markdown_options;
+
+	fn render(input: &str, sidenotes: bool) -> String {
+		let parser = pulldown_cmark::Parser::new_ext(input, markdown_options());
+		let mut output = String::new();
+		pulldown_cmark::html::push_html(
+			&mut output,
+			footnotes(parser, sidenotes).into_iter(),
+		);
+		output
+	}
+
+	#[test]
+	fn test_footnotes() {
+		let output = render(
+			"[^b]: Defined first.\n\nOne[^a], two[^b], one again[^a].\n\n[^a]: Referenced first.",
+			false,
+		);
+		let section = output.find("<section class=\"footnotes\">").unwrap();
+		// Everything is moved to the end, in order of reference.
+		assert!(!output[..section].contains("Defined first"));
+		assert!(
+			output.find("Referenced first").unwrap()
+				< output.find("Defined first").unwrap()
+		);
+		assert!(output.contains("id=\"fnref-1\"><a href=\"#fn-1\">1</a>"));
+		assert!(output.contains("id=\"fnref-1-2\""));
+		assert!(output.contains("href=\"#fnref-1-2\""));
+		assert!(output.contains("<li id=\"fn-2\">"));
+	}
+
+	#[test]
+	fn test_sidenotes() {
+		let output = render("Intro.\n\nText[^a].\n\n[^a]: A note.", true);
+		let sidenote = output.find("<aside class=\"sidenote\">").unwrap();
+		assert!(output.find("Intro.").unwrap() < sidenote);
+		assert!(sidenote < output.find("<p>Text").unwrap());
+	}
+}
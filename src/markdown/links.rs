@@ -0,0 +1,312 @@
+use pulldown_cmark::{CowStr, Event, LinkType, Tag};
+use std::{collections::HashMap, ops::Range};
+
+use crate::markdown::spans::replace_spans;
+
+/// Every post that can be linked to, by its file name without the extension
+/// (`2023-07-16_new_personal_website`) or, if it's unique, just the part
+/// after the date (`new_personal_website`).
+pub struct PostIndex {
+	posts: HashMap<String, PostRef>,
+	/// `None` when more than one post has the same short name.
+	short_names: HashMap<String, Option<PostRef>>,
+}
+
+#[derive(Clone)]
+pub struct PostRef {
+	pub href: String,
+	pub title: String,
+}
+
+impl PostIndex {
+	pub fn new<'a>(posts: impl IntoIterator<Item = (&'a str, PostRef)>) -> Self {
+		let mut index = Self {
+			posts: HashMap::new(),
+			short_names: HashMap::new(),
+		};
+		for (filename, post) in posts {
+			if let Some((_, short_name)) = filename.split_once('_') {
+				index
+					.short_names
+					.entry(short_name.to_string())
+					.and_modify(|existing| *existing = None)
+					.or_insert_with(|| Some(post.clone()));
+			}
+			index.posts.insert(filename.to_string(), post);
+		}
+		index
+	}
+
+	/// Look up a link target like `slug` or `slug#some-heading`, returning the
+	/// post and the full href to it.
+	pub fn resolve(&self, target: &str) -> Option<(&PostRef, String)> {
+		let (slug, fragment) = match target.split_once('#') {
+			Some((slug, fragment)) => (slug, Some(fragment)),
+			None => (target, None),
+		};
+		let slug = slug.trim();
+		let post = self
+			.posts
+			.get(slug)
+			.or_else(|| self.short_names.get(slug)?.as_ref())?;
+		let href = match fragment {
+			Some(fragment) => format!("{}#{}", post.href, fragment),
+			None => post.href.clone(),
+		};
+		Some((post, href))
+	}
+
+	/// Find the post a url points to, if it's one of these posts.
+	pub fn by_href(&self, url: &str) -> Option<&PostRef> {
+		let href = url.split_once('#').map_or(url, |(href, _)| href);
+		self.posts.values().find(|post| post.href == href)
+	}
+}
+
+#[derive(Debug)]
+pub struct UnresolvedLink {
+	pub line: usize,
+	pub target: String,
+}
+
+/// Resolve links between posts, written either as `[[slug]]`,
+/// `[[slug|link text]]` or `[link text](post:slug)`. Links that don't match a
+/// post are left as they are and added to `unresolved`.
+pub fn post_links<'a>(
+	events: Vec<(Event<'a>, Range<usize>)>,
+	source: &'a str,
+	posts: &PostIndex,
+	line_of: impl Fn(usize) -> usize,
+	unresolved: &mut Vec<UnresolvedLink>,
+) -> Vec<(Event<'a>, Range<usize>)> {
+	let mut replacements = Vec::new();
+
+	for (span, inner) in find_wiki_links(&events, source) {
+		let inner = &source[inner];
+		let (target, text) = match inner.split_once('|') {
+			Some((target, text)) => (target, Some(text.trim())),
+			None => (inner, None),
+		};
+		match posts.resolve(target) {
+			Some((post, href)) => {
+				let text = text.unwrap_or(&post.title).to_string();
+				let link = Tag::Link(
+					LinkType::Inline,
+					CowStr::from(href),
+					CowStr::from(post.title.clone()),
+				);
+				replacements.push((
+					span,
+					vec![
+						Event::Start(link.clone()),
+						Event::Text(CowStr::from(text)),
+						Event::End(link),
+					],
+				));
+			}
+			None => unresolved.push(UnresolvedLink {
+				line: line_of(span.start),
+				target: target.trim().to_string(),
+			}),
+		}
+	}
+
+	let events = replace_spans(events, source, replacements);
+
+	events
+		.into_iter()
+		.map(|(event, range)| match event {
+			Event::Start(Tag::Link(link_type, url, title))
+				if url.starts_with("post:") =>
+			{
+				let target = &url["post:".len()..];
+				match posts.resolve(target) {
+					Some((post, href)) => {
+						let title = if title.is_empty() {
+							CowStr::from(post.title.clone())
+						} else {
+							title
+						};
+						(
+							Event::Start(Tag::Link(link_type, CowStr::from(href), title)),
+							range,
+						)
+					}
+					None => {
+						unresolved.push(UnresolvedLink {
+							line: line_of(range.start),
+							target: target.to_string(),
+						});
+						(Event::Start(Tag::Link(link_type, url, title)), range)
+					}
+				}
+			}
+			event => (event, range),
+		})
+		.collect()
+}
+
+/// The hrefs of every post linked to in some markdown, in the order they're
+/// first linked.
+pub fn linked_posts(
+	events: &[(Event, Range<usize>)],
+	posts: &PostIndex,
+) -> Vec<String> {
+	let mut hrefs: Vec<String> = Vec::new();
+	for (event, _) in events {
+		if let Event::Start(Tag::Link(_, url, _)) = event {
+			if let Some(post) = posts.by_href(url) {
+				if !hrefs.contains(&post.href) {
+					hrefs.push(post.href.clone());
+				}
+			}
+		}
+	}
+	hrefs
+}
+
+/// Find every `[[...]]` in the text of some markdown, returning where the whole
+/// thing is and where the part inside the brackets is.
+fn find_wiki_links(
+	events: &[(Event, Range<usize>)],
+	source: &str,
+) -> Vec<(Range<usize>, Range<usize>)> {
+	let mut text_ranges = Vec::new();
+	let mut in_code_block = false;
+	for (event, range) in events {
+		match event {
+			Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+			Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+			Event::Text(_) if !in_code_block => text_ranges.push(range.clone()),
+			_ => {}
+		}
+	}
+	let in_text =
+		|pos: usize| text_ranges.iter().any(|range| range.contains(&pos));
+
+	let mut links = Vec::new();
+	let mut end = 0;
+	for range in &text_ranges {
+		for start in range.clone() {
+			if start < end || !source.as_bytes()[start..].starts_with(b"[[") {
+				continue;
+			}
+			let inner_start = start + 2;
+			let Some(len) = source[inner_start..].find("]]") else {
+				continue;
+			};
+			let inner = inner_start..inner_start + len;
+			let inner_text = &source[inner.clone()];
+			if inner_text.trim().is_empty()
+				|| inner_text.contains(['\n', '[', ']'])
+				|| !in_text(inner.end)
+			{
+				continue;
+			}
+			end = inner.end + 2;
+			links.push((start..end, inner));
+		}
+	}
+
+	links
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::post::markdown_options;
+
+	fn render(input: &str) -> (String, Vec<UnresolvedLink>) {
+		let posts = PostIndex::new([
+			(
+				"2023-07-16_new_personal_website",
+				PostRef {
+					href: "/posts/2023-07-16_new_personal_website.html".to_string(),
+					title: "New Personal Website".to_string(),
+				},
+			),
+			(
+				"2024-01-01_tags",
+				PostRef {
+					href: "/posts/2024-01-01_tags.html".to_string(),
+					title: "Tags".to_string(),
+				},
+			),
+		]);
+		let parser = pulldown_cmark::Parser::new_ext(input, markdown_options());
+		let mut unresolved = Vec::new();
+		let events = post_links(
+			parser.into_offset_iter().collect(),
+			input,
+			&posts,
+			|pos| input[..pos].matches('\n').count() + 1,
+			&mut unresolved,
+		);
+		let mut output = String::new();
+		pulldown_cmark::html::push_html(
+			&mut output,
+			events.into_iter().map(|(event, _)| event),
+		);
+		(output, unresolved)
+	}
+
+	#[test]
+	fn test_wiki_links() {
+		let (output, unresolved) = render(
+			"See [[2023-07-16_new_personal_website]], [[tags|this]] and [[tags#top]].",
+		);
+		assert!(unresolved.is_empty());
+		assert_eq!(
+			output,
+			"<p>See <a href=\"/posts/2023-07-16_new_personal_website.html\" title=\"New Personal Website\">New Personal Website</a>, \
+			<a href=\"/posts/2024-01-01_tags.html\" title=\"Tags\">this</a> and \
+			<a href=\"/posts/2024-01-01_tags.html#top\" title=\"Tags\">Tags</a>.</p>\n"
+		);
+	}
+
+	#[test]
+	fn test_post_scheme() {
+		let (output, unresolved) =
+			render("[the new site](post:new_personal_website)");
+		assert!(unresolved.is_empty());
+		assert!(output.contains(
+			"<a href=\"/posts/2023-07-16_new_personal_website.html\" title=\"New Personal Website\">the new site</a>"
+		));
+	}
+
+	#[test]
+	fn test_linked_posts() {
+		let posts = PostIndex::new([(
+			"2024-01-01_tags",
+			PostRef {
+				href: "/posts/2024-01-01_tags.html".to_string(),
+				title: "Tags".to_string(),
+			},
+		)]);
+		let input = "[[tags]], [again](/posts/2024-01-01_tags.html#top) and [elsewhere](/blog.html)";
+		let parser = pulldown_cmark::Parser::new_ext(input, markdown_options());
+		let events = post_links(
+			parser.into_offset_iter().collect(),
+			input,
+			&posts,
+			|_| 1,
+			&mut Vec::new(),
+		);
+		assert_eq!(
+			linked_posts(&events, &posts),
+			["/posts/2024-01-01_tags.html"]
+		);
+	}
+
+	#[test]
+	fn test_unresolved() {
+		let (output, unresolved) =
+			render("[[nope]]\n\n`[[in code]]`\n\n[x](post:also_nope)");
+		assert!(output.contains("[[nope]]"));
+		assert_eq!(unresolved.len(), 2);
+		assert_eq!(unresolved[0].target, "nope");
+		assert_eq!(unresolved[0].line, 1);
+		assert_eq!(unresolved[1].target, "also_nope");
+		assert_eq!(unresolved[1].line, 5);
+	}
+}
@@ -0,0 +1,185 @@
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::ops::Range;
+
+use crate::{
+	markdown::spans::replace_spans,
+	parsers::{error::ParsingError, latex::latex_to_mathml},
+};
+
+struct MathSpan {
+	/// Where the math is in the markdown, including its `$` delimiters.
+	range: Range<usize>,
+	tex: Range<usize>,
+	display: bool,
+}
+
+/// Replace `$inline$` and `$$display$$` math with MathML.
+///
+/// pulldown-cmark has no idea about math, so by the time it's done the LaTeX
+/// may have been split up by emphasis, escapes and so on. Instead, this finds
+/// the `$` delimiters in the text events and reads the LaTeX between them
+/// straight out of the markdown source, dropping whatever events were
+/// generated from it. `line_of` maps a position in `source` to a line in the
+/// post file for error messages.
+pub fn math<'a>(
+	events: Vec<(Event<'a>, Range<usize>)>,
+	source: &'a str,
+	line_of: impl Fn(usize) -> usize,
+) -> Result<Vec<(Event<'a>, Range<usize>)>, ParsingError> {
+	let replacements = find_math(&events, source)
+		.into_iter()
+		.map(|span| {
+			let mathml =
+				latex_to_mathml(&source[span.tex], span.display).map_err(|reason| {
+					ParsingError::InvalidMath {
+						line: line_of(span.range.start),
+						reason,
+					}
+				})?;
+			Ok((span.range, vec![Event::Html(CowStr::from(mathml.0))]))
+		})
+		.collect::<Result<Vec<_>, ParsingError>>()?;
+
+	Ok(replace_spans(events, source, replacements))
+}
+
+struct Delimiter {
+	pos: usize,
+	display: bool,
+	/// Which block of text the delimiter is in. Math can't span blocks.
+	block: usize,
+}
+
+fn find_math(events: &[(Event, Range<usize>)], source: &str) -> Vec<MathSpan> {
+	let mut delimiters = Vec::new();
+	let mut block = 0;
+	let mut in_code_block = false;
+
+	for (event, range) in events {
+		match event {
+			Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+			Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+			Event::Start(tag) | Event::End(tag) if is_block(tag) => block += 1,
+			Event::Text(text) if !in_code_block && text.contains('$') => {
+				let bytes = source.as_bytes();
+				let mut i = range.start;
+				while i < range.end {
+					if bytes[i] == b'$' && (i == 0 || bytes[i - 1] != b'\\') {
+						let display = bytes.get(i + 1) == Some(&b'$');
+						delimiters.push(Delimiter {
+							pos: i,
+							display,
+							block,
+						});
+						i += if display { 2 } else { 1 };
+					} else {
+						i += 1;
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+
+	let char_after = |pos: usize| source[pos..].chars().next();
+	let char_before = |pos: usize| source[..pos].chars().next_back();
+	// Same rules as pandoc, so that "$5 and $10" isn't math.
+	let can_open = |d: &Delimiter| {
+		d.display || char_after(d.pos + 1).is_some_and(|c| !c.is_whitespace())
+	};
+	let can_close = |d: &Delimiter| {
+		d.display
+			|| (char_before(d.pos).is_some_and(|c| !c.is_whitespace())
+				&& !char_after(d.pos + 1).is_some_and(|c| c.is_ascii_digit()))
+	};
+
+	let mut spans = Vec::new();
+	let mut open: Option<&Delimiter> = None;
+
+	for delimiter in &delimiters {
+		match open {
+			Some(o)
+				if o.block == delimiter.block
+					&& o.display == delimiter.display
+					&& can_close(delimiter) =>
+			{
+				let len = if o.display { 2 } else { 1 };
+				spans.push(MathSpan {
+					range: o.pos..delimiter.pos + len,
+					tex: o.pos + len..delimiter.pos,
+					display: o.display,
+				});
+				open = None;
+			}
+			Some(o) if o.block == delimiter.block => {}
+			_ => open = can_open(delimiter).then_some(delimiter),
+		}
+	}
+
+	spans
+}
+
+fn is_block(tag: &Tag) -> bool {
+	matches!(
+		tag,
+		Tag::Paragraph
+			| Tag::Heading(..)
+			| Tag::BlockQuote
+			| Tag::List(_)
+			| Tag::Item
+			| Tag::FootnoteDefinition(_)
+			| Tag::Table(_)
+			| Tag::TableHead
+			| Tag::TableRow
+			| Tag::TableCell
+	)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::post::markdown_options;
+
+	fn render(input: &str) -> Result<String, ParsingError> {
+		let parser = pulldown_cmark::Parser::new_ext(input, markdown_options());
+		let events = math(parser.into_offset_iter().collect(), input, |pos| {
+			input[..pos].matches('\n').count() + 1
+		})?;
+		let mut output = String::new();
+		pulldown_cmark::html::push_html(
+			&mut output,
+			events.into_iter().map(|(event, _)| event),
+		);
+		Ok(output)
+	}
+
+	#[test]
+	fn test_inline() {
+		let output = render("a *total* of $a_i *b* c_i$ things").unwrap();
+		assert!(output.starts_with("<p>a <em>total</em> of <math"));
+		assert!(output.contains("</math> things</p>"));
+		assert!(!output.contains("<em>b</em>"));
+	}
+
+	#[test]
+	fn test_display() {
+		let output = render("$$\n\\frac{1}{2}\n$$").unwrap();
+		assert!(output.contains(
+			"<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\">"
+		));
+		assert!(output.contains("<mfrac>"));
+	}
+
+	#[test]
+	fn test_not_math() {
+		let input = "costs $5 and $10, or \\$x\\$, or `$y$`";
+		let output = render(input).unwrap();
+		assert!(!output.contains("<math"));
+	}
+
+	#[test]
+	fn test_error_line() {
+		let error = render("one\n\ntwo $\\bingus$").unwrap_err();
+		assert!(matches!(error, ParsingError::InvalidMath { line: 3, .. }));
+	}
+}
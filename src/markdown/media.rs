@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use maud::{html, Markup};
+
+use crate::{
+	images::{ResponsiveImages, Srcset},
+	util::url_to_static_path,
+};
+
+/// Extensions a poster frame for a video may have. A poster is picked up
+/// automatically when a file with the same name as the video and one of these
+/// extensions sits next to it, e.g. `clip.mp4` and `clip.jpg`.
+const POSTER_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+pub enum Media {
+	Image,
+	Video { mime: &'static str },
+	Audio { mime: &'static str },
+}
+
+impl Media {
+	/// Guess what kind of media a markdown image link points at from the
+	/// extension of its url.
+	pub fn from_url(url: &str) -> Self {
+		let path = url.split(['?', '#']).next().unwrap_or(url);
+		let extension = path
+			.rsplit_once('.')
+			.map(|(_, ext)| ext.to_ascii_lowercase())
+			.unwrap_or_default();
+
+		match extension.as_str() {
+			"mp4" => Self::Video { mime: "video/mp4" },
+			"webm" => Self::Video { mime: "video/webm" },
+			"mp3" => Self::Audio { mime: "audio/mpeg" },
+			"ogg" => Self::Audio { mime: "audio/ogg" },
+			_ => Self::Image,
+		}
+	}
+}
+
+/// Render the target of markdown image syntax, `![alt](url 'title')`, as an
+/// `img`, `video` or `audio` element, with the title as a caption underneath.
+/// Local images get a `srcset` of resized copies made by `images`.
+pub fn render_media(
+	url: &str,
+	title: &str,
+	alt: &str,
+	images: &ResponsiveImages,
+) -> Markup {
+	let media = html! {
+		@match Media::from_url(url) {
+			Media::Image => {
+				@if let Some(srcset) = images.srcset(url) {
+					@if let Some(webp_srcset) = srcset.webp_srcset() {
+						picture {
+							source type="image/webp" srcset=(webp_srcset) sizes=(srcset.sizes);
+							(responsive_img(url, title, alt, &srcset))
+						}
+					} @else {
+						(responsive_img(url, title, alt, &srcset))
+					}
+				} @else {
+					img src=(url) alt=(alt) title=(title);
+				}
+			}
+			Media::Video { mime } => {
+				video controls preload="metadata" poster=[find_poster(url)] {
+					source src=(url) type=(mime);
+					(fallback_link(url, alt))
+				}
+			}
+			Media::Audio { mime } => {
+				audio controls preload="metadata" {
+					source src=(url) type=(mime);
+					(fallback_link(url, alt))
+				}
+			}
+		}
+	};
+
+	html! {
+		(media)
+		@if !title.trim().is_empty() {
+			p class="markdown-image-title" { (title) }
+		}
+	}
+}
+
+fn responsive_img(
+	url: &str,
+	title: &str,
+	alt: &str,
+	srcset: &Srcset,
+) -> Markup {
+	html! {
+		img
+			src=(url)
+			srcset=(srcset.srcset())
+			sizes=(srcset.sizes)
+			width=(srcset.width)
+			height=(srcset.height)
+			loading="lazy"
+			alt=(alt)
+			title=(title);
+	}
+}
+
+fn fallback_link(url: &str, alt: &str) -> Markup {
+	html! {
+		a href=(url) {
+			@if alt.trim().is_empty() { (url) } @else { (alt) }
+		}
+	}
+}
+
+fn find_poster(url: &str) -> Option<String> {
+	poster_next_to(url, &url_to_static_path(url)?)
+}
+
+/// The url of a poster for the video at `url`, whose file is at `path`.
+fn poster_next_to(url: &str, path: &Path) -> Option<String> {
+	let (url_stem, _) = url.rsplit_once('.')?;
+
+	POSTER_EXTENSIONS.iter().find_map(|ext| {
+		path
+			.with_extension(ext)
+			.is_file()
+			.then(|| format!("{}.{}", url_stem, ext))
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::images::ImageOptions;
+
+	fn mime(url: &str) -> Option<&'static str> {
+		match Media::from_url(url) {
+			Media::Image => None,
+			Media::Video { mime } | Media::Audio { mime } => Some(mime),
+		}
+	}
+
+	#[test]
+	fn test_from_url() {
+		assert_eq!(mime("/static/a.mp4"), Some("video/mp4"));
+		assert_eq!(mime("a.MP4?x=1"), Some("video/mp4"));
+		assert_eq!(mime("a.WebM#t=10"), Some("video/webm"));
+		assert_eq!(mime("a.mp3"), Some("audio/mpeg"));
+		assert_eq!(mime("a.Ogg?x.mp4"), Some("audio/ogg"));
+		assert!(matches!(Media::from_url("a.mp4.png"), Media::Image));
+		assert!(matches!(Media::from_url("mp4"), Media::Image));
+		assert!(matches!(Media::from_url("a.png?x=.mp4"), Media::Image));
+	}
+
+	#[test]
+	fn test_poster() {
+		let dir =
+			std::env::temp_dir().join(format!("poster-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let video = dir.join("clip.mp4");
+		assert_eq!(poster_next_to("/static/clip.mp4", &video), None);
+		std::fs::write(dir.join("clip.png"), "").unwrap();
+		assert_eq!(
+			poster_next_to("/static/clip.mp4", &video).as_deref(),
+			Some("/static/clip.png")
+		);
+		// A jpg wins over a png.
+		std::fs::write(dir.join("clip.jpg"), "").unwrap();
+		assert_eq!(
+			poster_next_to("/static/clip.mp4", &video).as_deref(),
+			Some("/static/clip.jpg")
+		);
+		std::fs::remove_dir_all(dir).unwrap();
+
+		// Only local videos can have one.
+		assert_eq!(find_poster("https://a.com/clip.mp4"), None);
+	}
+
+	#[test]
+	fn test_render_media() {
+		let images = ResponsiveImages::new(ImageOptions::default());
+		let video = render_media("/static/a.mp4", "", "A clip", &images).0;
+		assert_eq!(
+			video,
+			r#"<video controls preload="metadata"><source src="/static/a.mp4" type="video/mp4"><a href="/static/a.mp4">A clip</a></video>"#
+		);
+		let audio = render_media("a.ogg", "Song", " ", &images).0;
+		assert_eq!(
+			audio,
+			r#"<audio controls preload="metadata"><source src="a.ogg" type="audio/ogg"><a href="a.ogg">a.ogg</a></audio><p class="markdown-image-title">Song</p>"#
+		);
+	}
+}
@@ -0,0 +1,478 @@
+use maud::{html, Markup, PreEscaped};
+use pulldown_cmark::{Event, Tag};
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+	markdown::callout::{callout_start, CalloutKind, CALLOUT_END},
+	parsers::{
+		combinators::{
+			delimited, in_set, many0, map, pair, tag, ws, PResult, ParserError,
+		},
+		error::ParsingError,
+	},
+	post::markdown_options,
+};
+
+/// A shortcode as written in a post, either on its own,
+/// `{{< youtube id="dQw4w9WgXcQ" >}}`, or wrapping some markdown,
+/// `{{< note kind="warning" >}}...{{< /note >}}`.
+pub struct Shortcode<'a> {
+	pub name: &'a str,
+	pub args: HashMap<&'a str, &'a str>,
+	pub body: Option<String>,
+}
+
+impl<'a> Shortcode<'a> {
+	pub fn arg(&self, key: &str) -> Option<&'a str> {
+		self.args.get(key).copied()
+	}
+
+	pub fn required_arg(&self, key: &str) -> Result<&'a str, String> {
+		self
+			.arg(key)
+			.ok_or_else(|| format!("missing argument \"{}\"", key))
+	}
+
+	pub fn required_body(&self) -> Result<&str, String> {
+		self
+			.body
+			.as_deref()
+			.ok_or_else(|| format!("missing closing {{{{< /{} >}}}}", self.name))
+	}
+}
+
+pub type ShortcodeFn = fn(&Shortcode) -> Result<Markup, String>;
+
+pub struct ShortcodeRegistry {
+	shortcodes: HashMap<&'static str, ShortcodeFn>,
+}
+
+impl Default for ShortcodeRegistry {
+	fn default() -> Self {
+		Self::new()
+			.register("note", note)
+			.register("youtube", youtube)
+			.register("details", details)
+	}
+}
+
+impl ShortcodeRegistry {
+	/// An empty registry, see [`ShortcodeRegistry::default`] for one with the
+	/// built-in shortcodes.
+	pub fn new() -> Self {
+		Self {
+			shortcodes: HashMap::new(),
+		}
+	}
+
+	pub fn register(mut self, name: &'static str, f: ShortcodeFn) -> Self {
+		self.shortcodes.insert(name, f);
+		self
+	}
+
+	/// Replace every shortcode in a post's markdown with the html it renders
+	/// to. Shortcodes inside code spans and code blocks are left alone.
+	/// `line_offset` is the number of lines in the file before `input`, so
+	/// errors point at the right line, and the returned [`SourceMap`] lets
+	/// later stages do the same for positions in the expanded markdown.
+	pub fn expand(
+		&self,
+		input: &str,
+		line_offset: usize,
+	) -> Result<(String, SourceMap), ParsingError> {
+		let code = code_ranges(input);
+		let mut output = String::with_capacity(input.len());
+		let mut anchors = Vec::new();
+
+		self.expand_range(
+			input,
+			0..input.len(),
+			line_offset,
+			&code,
+			&mut output,
+			&mut anchors,
+		)?;
+
+		let source_map = SourceMap {
+			anchors,
+			newlines: input.match_indices('\n').map(|(i, _)| i).collect(),
+			line_offset,
+		};
+		Ok((output, source_map))
+	}
+
+	fn expand_range(
+		&self,
+		input: &str,
+		range: Range<usize>,
+		line_offset: usize,
+		code: &[Range<usize>],
+		output: &mut String,
+		anchors: &mut Vec<(usize, usize)>,
+	) -> Result<(), ParsingError> {
+		let line_of =
+			|pos: usize| line_offset + input[..pos].matches('\n').count() + 1;
+		let input_end = &input[..range.end];
+
+		let mut pos = range.start;
+
+		while let Some(start) = find_shortcode(input_end, pos, code) {
+			anchors.push((output.len(), pos));
+			output.push_str(&input[pos..start]);
+			let line = line_of(start);
+
+			let (open, after_open) =
+				open_tag(&input_end[start..]).map_err(|error| {
+					ParsingError::Shortcode {
+						line,
+						reason: format!("{:?}", error),
+					}
+				})?;
+			let after_open = range.end - after_open.len();
+
+			let (name, args) = match open {
+				OpenTag::Open { name, args } => (name, args),
+				OpenTag::Close { name } => {
+					return Err(ParsingError::Shortcode {
+						line,
+						reason: format!("closing {{{{< /{} >}}}} without an opening", name),
+					})
+				}
+			};
+
+			let f = self.shortcodes.get(name).ok_or_else(|| {
+				ParsingError::UnknownShortcode {
+					name: name.to_string(),
+					line,
+				}
+			})?;
+
+			let mut body_anchors = Vec::new();
+			let (body, end) = match find_close(input_end, after_open, name, code) {
+				Some((body_end, end)) => {
+					let mut body = String::new();
+					self.expand_range(
+						input,
+						after_open..body_end,
+						line_offset,
+						code,
+						&mut body,
+						&mut body_anchors,
+					)?;
+					(Some(body), end)
+				}
+				None => (None, after_open),
+			};
+
+			let shortcode = Shortcode { name, args, body };
+			let markup = f(&shortcode).map_err(|reason| ParsingError::Shortcode {
+				line,
+				reason: format!("{}: {}", name, reason),
+			})?;
+
+			anchors.push((output.len(), start));
+			// If the body made it into the output unchanged, positions inside it
+			// can still be traced back to the original.
+			if let Some(body) = &shortcode.body {
+				if let Some(i) = markup.0.find(body.as_str()) {
+					let body_start = output.len() + i;
+					anchors.extend(
+						body_anchors
+							.into_iter()
+							.map(|(out, original)| (body_start + out, original)),
+					);
+				}
+			}
+			output.push_str(&markup.0);
+
+			pos = end;
+		}
+
+		anchors.push((output.len(), pos));
+		output.push_str(&input[pos..range.end]);
+		Ok(())
+	}
+}
+
+/// Maps positions in expanded markdown back to lines in the post file it came
+/// from.
+#[derive(Debug)]
+pub struct SourceMap {
+	/// Pairs of (position in the output, position in the input) where a
+	/// stretch of copied or generated text starts.
+	anchors: Vec<(usize, usize)>,
+	/// Position of every newline in the input.
+	newlines: Vec<usize>,
+	line_offset: usize,
+}
+
+impl SourceMap {
+	/// The line in the post file that a position in the expanded markdown came
+	/// from.
+	pub fn line(&self, pos: usize) -> usize {
+		let i = self.anchors.partition_point(|(out, _)| *out <= pos);
+		let original = match i {
+			0 => pos,
+			i => {
+				let (out, original) = self.anchors[i - 1];
+				original + (pos - out)
+			}
+		};
+		self.line_offset
+			+ self.newlines.partition_point(|newline| *newline < original)
+			+ 1
+	}
+}
+
+#[derive(Debug)]
+enum OpenTag<'a> {
+	Open {
+		name: &'a str,
+		args: HashMap<&'a str, &'a str>,
+	},
+	Close {
+		name: &'a str,
+	},
+}
+
+const NAME_CHARS: &str =
+	"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_-";
+
+fn open_tag(input: &str) -> PResult<'_, OpenTag<'_>> {
+	let (inner, rest) = delimited("{{<", ">}}")(input)?;
+	let (_, inner) = ws(inner)?;
+
+	if let Ok((_, inner)) = tag("/")(inner) {
+		let (name, inner) = in_set(NAME_CHARS)(inner)?;
+		let (_, inner) = ws(inner)?;
+		if name.is_empty() || !inner.is_empty() {
+			return Err(ParserError::Detailed(format!(
+				"invalid closing shortcode \"{}\"",
+				input
+			)));
+		}
+		return Ok((OpenTag::Close { name }, rest));
+	}
+
+	let (name, inner) = in_set(NAME_CHARS)(inner)?;
+	if name.is_empty() {
+		return Err(ParserError::Detailed("missing shortcode name".to_string()));
+	}
+
+	let arg = map(
+		pair(
+			pair(ws, pair(in_set(NAME_CHARS), tag("="))),
+			delimited("\"", "\""),
+		),
+		|((_, (key, _)), value)| (key, value),
+	);
+	let (args, inner) = many0(arg)(inner)?;
+	let (_, inner) = ws(inner)?;
+	if !inner.is_empty() {
+		return Err(ParserError::Detailed(format!(
+			"unexpected \"{}\" in shortcode {}",
+			inner, name
+		)));
+	}
+
+	Ok((
+		OpenTag::Open {
+			name,
+			args: args.into_iter().collect(),
+		},
+		rest,
+	))
+}
+
+/// Position of the next `{{<` at or after `from` that isn't inside code.
+fn find_shortcode(
+	input: &str,
+	from: usize,
+	code: &[Range<usize>],
+) -> Option<usize> {
+	let mut from = from;
+	while let Some(i) = input[from..].find("{{<") {
+		let i = from + i;
+		if !code.iter().any(|range| range.contains(&i)) {
+			return Some(i);
+		}
+		from = i + 3;
+	}
+	None
+}
+
+/// Find the `{{< /name >}}` matching a shortcode opened just before `from`,
+/// skipping over any nested shortcodes with the same name. Returns where the
+/// body ends and where the closing tag ends.
+fn find_close(
+	input: &str,
+	from: usize,
+	name: &str,
+	code: &[Range<usize>],
+) -> Option<(usize, usize)> {
+	let mut depth = 0;
+	let mut pos = from;
+	while let Some(start) = find_shortcode(input, pos, code) {
+		let Ok((tag, rest)) = open_tag(&input[start..]) else {
+			pos = start + 3;
+			continue;
+		};
+		pos = input.len() - rest.len();
+		match tag {
+			OpenTag::Open { name: n, .. } if n == name => depth += 1,
+			OpenTag::Close { name: n } if n == name => {
+				if depth == 0 {
+					return Some((start, pos));
+				}
+				depth -= 1;
+			}
+			_ => {}
+		}
+	}
+	None
+}
+
+/// Byte ranges of every code span and code block in some markdown.
+fn code_ranges(input: &str) -> Vec<Range<usize>> {
+	pulldown_cmark::Parser::new_ext(input, markdown_options())
+		.into_offset_iter()
+		.filter_map(|(event, range)| match event {
+			Event::Code(_) | Event::Start(Tag::CodeBlock(_)) => Some(range),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Wrap markdown so it's still parsed as markdown once it's been placed
+/// inside an html block.
+fn markdown_body(body: &str) -> PreEscaped<String> {
+	PreEscaped(format!("\n\n{}\n\n", body))
+}
+
+/// `{{< note kind="warning" title="Heads up" >}}...{{< /note >}}`
+fn note(shortcode: &Shortcode) -> Result<Markup, String> {
+	let kind = match shortcode.arg("kind") {
+		Some(kind) => CalloutKind::parse(kind)
+			.ok_or_else(|| format!("unknown kind \"{}\"", kind))?,
+		None => CalloutKind::Note,
+	};
+	let body = shortcode.required_body()?;
+
+	Ok(html! {
+		(PreEscaped(callout_start(kind, shortcode.arg("title"))))
+		(markdown_body(body))
+		(PreEscaped(CALLOUT_END))
+	})
+}
+
+/// `{{< youtube id="dQw4w9WgXcQ" title="..." >}}`
+fn youtube(shortcode: &Shortcode) -> Result<Markup, String> {
+	let id = shortcode.required_arg("id")?;
+	if id.is_empty() || !id.chars().all(|c| NAME_CHARS.contains(c)) {
+		return Err(format!("invalid video id \"{}\"", id));
+	}
+
+	Ok(html! {
+		.video-embed {
+			iframe
+				src=(format!("https://www.youtube-nocookie.com/embed/{}", id))
+				title=(shortcode.arg("title").unwrap_or("YouTube video"))
+				loading="lazy"
+				referrerpolicy="strict-origin-when-cross-origin"
+				allow="encrypted-media; picture-in-picture"
+				allowfullscreen {}
+		}
+	})
+}
+
+/// `{{< details summary="Click to expand" >}}...{{< /details >}}`
+fn details(shortcode: &Shortcode) -> Result<Markup, String> {
+	let summary = shortcode.arg("summary").unwrap_or("Details");
+	let body = shortcode.required_body()?;
+
+	Ok(html! {
+		details {
+			summary { (summary) }
+			(markdown_body(body))
+		}
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn expand(input: &str) -> Result<String, ParsingError> {
+		ShortcodeRegistry::default()
+			.expand(input, 0)
+			.map(|(output, _)| output)
+	}
+
+	#[test]
+	fn test_open_tag() {
+		let (tag, rest) =
+			open_tag("{{< note kind=\"tip\" title=\"A title\" >}}!").unwrap();
+		let OpenTag::Open { name, args } = tag else {
+			panic!("expected an opening tag");
+		};
+		assert_eq!(name, "note");
+		assert_eq!(args.get("kind"), Some(&"tip"));
+		assert_eq!(args.get("title"), Some(&"A title"));
+		assert_eq!(rest, "!");
+
+		let (tag, _) = open_tag("{{< /note >}}").unwrap();
+		assert!(matches!(tag, OpenTag::Close { name: "note" }));
+
+		open_tag("{{< >}}").unwrap_err();
+		open_tag("{{< note kind=tip >}}").unwrap_err();
+	}
+
+	#[test]
+	fn test_expand() {
+		let output = expand(
+			"before\n\n{{< details summary=\"More\" >}}\n*inside*\n{{< /details >}}\n\nafter",
+		)
+		.unwrap();
+		assert_eq!(
+			output,
+			"before\n\n<details><summary>More</summary>\n\n\n*inside*\n\n\n</details>\n\nafter"
+		);
+	}
+
+	#[test]
+	fn test_nested() {
+		let output = expand(
+			"{{< details >}}\n{{< details >}}\ninner\n{{< /details >}}\n{{< /details >}}",
+		)
+		.unwrap();
+		assert_eq!(output.matches("<details>").count(), 2);
+		assert_eq!(output.matches("</details>").count(), 2);
+	}
+
+	#[test]
+	fn test_code_is_ignored() {
+		let input = "`{{< nope >}}`\n\n```\n{{< nope >}}\n```\n";
+		assert_eq!(expand(input).unwrap(), input);
+	}
+
+	#[test]
+	fn test_source_map() {
+		let input = "X\n{{< details >}}\nb\nY\n{{< /details >}}\nZ\n";
+		let (output, source_map) =
+			ShortcodeRegistry::default().expand(input, 10).unwrap();
+		assert_eq!(source_map.line(output.find('X').unwrap()), 11);
+		assert_eq!(source_map.line(output.find('Y').unwrap()), 14);
+		assert_eq!(source_map.line(output.find('Z').unwrap()), 16);
+	}
+
+	#[test]
+	fn test_unknown_shortcode() {
+		let error = ShortcodeRegistry::default()
+			.expand("one\ntwo\n{{< bingus >}}", 5)
+			.unwrap_err();
+		assert!(matches!(
+			error,
+			ParsingError::UnknownShortcode { ref name, line: 8 } if name == "bingus"
+		));
+	}
+}
@@ -0,0 +1,79 @@
+use pulldown_cmark::{CowStr, Event};
+use std::ops::Range;
+
+/// Replace stretches of markdown source with other events.
+///
+/// Used for syntax pulldown-cmark doesn't know about, which it turns into
+/// plain text (and possibly emphasis and such). Text events are split where a
+/// replacement starts or ends, and any other events entirely inside one are
+/// dropped. Replacements have to start in a text event, be sorted and not
+/// overlap.
+pub fn replace_spans<'a>(
+	events: Vec<(Event<'a>, Range<usize>)>,
+	source: &'a str,
+	replacements: Vec<(Range<usize>, Vec<Event<'a>>)>,
+) -> Vec<(Event<'a>, Range<usize>)> {
+	if replacements.is_empty() {
+		return events;
+	}
+
+	let mut replacements = replacements
+		.into_iter()
+		.map(|(range, events)| (range, Some(events)))
+		.collect::<Vec<_>>();
+	let mut output = Vec::with_capacity(events.len());
+
+	for (event, range) in events {
+		let text = match event {
+			Event::Text(text) => text,
+			event => {
+				let replaced = replacements
+					.iter()
+					.any(|(span, _)| span.start <= range.start && range.end <= span.end);
+				if !replaced {
+					output.push((event, range));
+				}
+				continue;
+			}
+		};
+
+		// Text that started inside a replacement is cut off where it ends.
+		let mut cursor = replacements
+			.iter()
+			.find(|(span, _)| span.start < range.start && range.start < span.end)
+			.map(|(span, _)| span.end)
+			.unwrap_or(range.start);
+
+		let starting_here = replacements
+			.iter_mut()
+			.filter(|(span, _)| range.contains(&span.start))
+			.collect::<Vec<_>>();
+
+		if cursor == range.start && starting_here.is_empty() {
+			output.push((Event::Text(text), range));
+			continue;
+		}
+
+		for (span, events) in starting_here {
+			if cursor < span.start {
+				output.push((
+					Event::Text(CowStr::Borrowed(&source[cursor..span.start])),
+					cursor..span.start,
+				));
+			}
+			for event in events.take().unwrap_or_default() {
+				output.push((event, span.clone()));
+			}
+			cursor = span.end;
+		}
+
+		if cursor < range.end {
+			output.push((
+				Event::Text(CowStr::Borrowed(&source[cursor..range.end])),
+				cursor..range.end,
+			));
+		}
+	}
+
+	output
+}
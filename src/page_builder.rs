@@ -1,9 +1,45 @@
-use crate::{error::Error, Result};
+use crate::{
+	error::Error,
+	styles::Stylesheet,
+	templates::{nav_vars, Layout, Templates, Vars},
+	Result,
+};
 use maud::{html, Markup, PreEscaped};
-use std::path::PathBuf;
+use minijinja::Value;
+use std::{path::PathBuf, rc::Rc};
 
 pub type TemplateFn = fn(&PageBuilder) -> Result<Markup>;
 
+/// A link in the site header or footer, from `site.toml` or a page in
+/// `pages/`.
+#[derive(Clone)]
+pub struct NavLink {
+	pub label: String,
+	pub href: String,
+	/// Opens in a new tab.
+	pub external: bool,
+	/// Shown before the label.
+	pub icon: Option<String>,
+}
+
+impl NavLink {
+	/// The link, marked as the current page if it goes to `active`.
+	fn render(&self, active: Option<&str>) -> Markup {
+		html! {
+			a href=(self.href)
+				target=[self.external.then_some("_blank")]
+				rel=[self.external.then_some("noopener")]
+				aria-current=[(active == Some(self.href.as_str())).then_some("page")]
+			{
+				@if let Some(icon) = &self.icon {
+					img.nav-icon src=(icon) alt="";
+				}
+				(self.label)
+			}
+		}
+	}
+}
+
 #[derive(Clone)]
 pub struct PageBuilder {
 	title: Option<String>,
@@ -11,6 +47,25 @@ pub struct PageBuilder {
 	description: Option<String>,
 	template: Option<TemplateFn>,
 	head: Option<Markup>,
+	nav: Vec<NavLink>,
+	footer: Vec<NavLink>,
+	/// The href of the page being written, filled in by
+	/// [`write`](PageBuilder::write).
+	active: Option<String>,
+	templates: Rc<Templates>,
+	/// The layout the body was made with, and the variables for a template
+	/// replacing it.
+	layout: Layout,
+	vars: Vars,
+	stylesheet: Rc<Stylesheet>,
+	/// Put the stylesheet in the page even if it has its own file.
+	inline_css: bool,
+}
+
+impl Default for PageBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl PageBuilder {
@@ -20,6 +75,14 @@ impl PageBuilder {
 			body: None,
 			description: None,
 			head: None,
+			nav: Vec::new(),
+			footer: Vec::new(),
+			active: None,
+			templates: Rc::default(),
+			layout: Layout::Base,
+			vars: Vars::new(),
+			stylesheet: Rc::default(),
+			inline_css: false,
 			template: Some(base_template),
 		}
 	}
@@ -44,6 +107,42 @@ impl PageBuilder {
 		self
 	}
 
+	pub fn nav(mut self, links: Vec<NavLink>) -> Self {
+		self.nav = links;
+		self
+	}
+
+	pub fn footer(mut self, links: Vec<NavLink>) -> Self {
+		self.footer = links;
+		self
+	}
+
+	/// Templates from `templates/` to use instead of the built-in layouts.
+	pub fn templates(mut self, templates: Rc<Templates>) -> Self {
+		self.templates = templates;
+		self
+	}
+
+	/// Say the body was made with `layout`, so that a template for it can be
+	/// used instead, with `vars`.
+	pub fn layout(mut self, layout: Layout, vars: Vars) -> Self {
+		self.layout = layout;
+		self.vars = vars;
+		self
+	}
+
+	pub fn stylesheet(mut self, stylesheet: Rc<Stylesheet>) -> Self {
+		self.stylesheet = stylesheet;
+		self
+	}
+
+	/// Inline the stylesheet, for pages that have to look right even if it
+	/// can't be loaded.
+	pub fn inline_css(mut self) -> Self {
+		self.inline_css = true;
+		self
+	}
+
 	pub fn no_template(mut self) -> Self {
 		self.template = None;
 		self
@@ -54,9 +153,28 @@ impl PageBuilder {
 
 		let output_path = PathBuf::from("./output/").join(&path);
 		println!("📄 {}", path.as_os_str().to_string_lossy());
+		if let Some(dir) = output_path.parent() {
+			std::fs::create_dir_all(dir)?;
+		}
 
 		if let Some(template) = &self.template {
-			std::fs::write(&output_path, template(self)?.0)?;
+			let mut page = Self {
+				active: Some(format!("/{}", path.to_string_lossy())),
+				..self.clone()
+			};
+			if page.layout != Layout::Base {
+				let mut vars = page.vars.clone();
+				vars.insert("body".to_string(), page.body_var());
+				if let Some(body) = page.templates.render(&page.layout, &vars) {
+					page.body = Some(PreEscaped(body?));
+				}
+			}
+			let html = match page.templates.render(&Layout::Base, &page.base_vars()?)
+			{
+				Some(html) => html?,
+				None => template(&page)?.0,
+			};
+			std::fs::write(&output_path, html)?;
 		} else {
 			if let Some(children) = &self.body {
 				std::fs::write(&output_path, &children.0)?;
@@ -69,17 +187,66 @@ impl PageBuilder {
 	}
 }
 
+impl PageBuilder {
+	fn body_var(&self) -> Value {
+		Value::from(
+			self
+				.body
+				.as_ref()
+				.map(|body| body.0.clone())
+				.unwrap_or_default(),
+		)
+	}
+
+	/// A `<link>` to the stylesheet, or a `<style>` with all of it.
+	fn stylesheet_html(&self) -> Markup {
+		html! {
+			@match (&self.stylesheet.href, self.inline_css) {
+				(Some(href), false) => {
+					link rel="stylesheet" href=(href);
+				}
+				_ => {
+					style {
+						(PreEscaped(&self.stylesheet.css))
+					}
+				}
+			}
+		}
+	}
+
+	/// Variables for a `base` template.
+	fn base_vars(&self) -> Result<Vars> {
+		let string =
+			|s: &Option<String>| Value::from(s.clone().unwrap_or_default());
+		let active = self.active.as_deref();
+		Ok(Vars::from([
+			("title".to_string(), string(&self.title)),
+			("description".to_string(), string(&self.description)),
+			(
+				"head".to_string(),
+				string(&self.head.as_ref().map(|head| head.0.clone())),
+			),
+			("body".to_string(), self.body_var()),
+			("css".to_string(), Value::from(self.stylesheet.css.clone())),
+			(
+				"stylesheet".to_string(),
+				Value::from(self.stylesheet_html().0),
+			),
+			("nav".to_string(), nav_vars(&self.nav, active)),
+			("footer".to_string(), nav_vars(&self.footer, active)),
+		]))
+	}
+}
+
 pub fn base_template(builder: &PageBuilder) -> Result<Markup> {
-	let css = std::fs::read_to_string("./styles.css")?;
+	let active = builder.active.as_deref();
 
 	Ok(html! {
 		html {
 			(maud::DOCTYPE)
 			head {
 				meta name="viewport" content="width=device-width, initial-scale=1.0";
-				style {
-					(PreEscaped(css))
-				}
+				(builder.stylesheet_html())
 				link rel="alternate" type="application/rss+xml" title="RSS" href="/feed.xml";
 				@if let Some(title) = &builder.title {
 					title { (&title) }
@@ -101,11 +268,9 @@ pub fn base_template(builder: &PageBuilder) -> Result<Markup> {
 						}
 
 						nav #site-links {
-							// a href="/about" { "about" }
-							a href="/blog.html" { "blog" }
-							a href="/feed.xml" { "rss" }
-							a href="https://bsky.app/profile/mcpar.land" target="_blank" { "bsky" }
-							a href="https://github.com/mcpar-land" target="_blank" { "github" }
+							@for link in &builder.nav {
+								(link.render(active))
+							}
 						}
 					}
 					#children {
@@ -118,13 +283,8 @@ pub fn base_template(builder: &PageBuilder) -> Result<Markup> {
 						// 	("© John McParland 2024");
 						// 	(chrono::offset::Utc::now().format("%Y"))
 						// }
-						div {
-							a href="/site.zip" { "Download Site" }
-						}
-						div {
-							a href="https://github.com/mcpar-land/mcpar-land.github.io" {
-								"Repo"
-							}
+						@for link in &builder.footer {
+							div { (link.render(active)) }
 						}
 					}
 				}
@@ -132,3 +292,73 @@ pub fn base_template(builder: &PageBuilder) -> Result<Markup> {
 		}
 	})
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_stylesheet() {
+		let external = Rc::new(Stylesheet {
+			css: "a{color: red}".to_string(),
+			href: Some("/static/styles.0123456789abcdef.css".to_string()),
+		});
+		let builder = PageBuilder::new().stylesheet(external.clone());
+		let linked = base_template(&builder).unwrap().0;
+		assert!(linked.contains(
+			r#"<link rel="stylesheet" href="/static/styles.0123456789abcdef.css">"#
+		));
+		assert!(!linked.contains("<style>"));
+
+		// Like the 404 page.
+		let inlined = base_template(&builder.inline_css()).unwrap().0;
+		assert!(inlined.contains("<style>a{color: red}</style>"));
+		assert!(!inlined.contains("<link rel=\"stylesheet\""));
+
+		let inline = Rc::new(Stylesheet {
+			css: "a{color: red}".to_string(),
+			href: None,
+		});
+		let inlined = base_template(&PageBuilder::new().stylesheet(inline))
+			.unwrap()
+			.0;
+		assert!(inlined.contains("<style>a{color: red}</style>"));
+	}
+
+	#[test]
+	fn test_current_link() {
+		let link = |label: &str, href: &str| NavLink {
+			label: label.to_string(),
+			href: href.to_string(),
+			external: href.starts_with("https://"),
+			icon: None,
+		};
+		let builder = PageBuilder::new()
+			.nav(vec![
+				link("blog", "/blog.html"),
+				link("about", "/about.html"),
+				link("github", "https://github.com/mcpar-land"),
+			])
+			.footer(vec![link("Download Site", "/site.zip")]);
+		// What write gives the page at blog.html.
+		let page = PageBuilder {
+			active: Some("/blog.html".to_string()),
+			..builder.clone()
+		};
+
+		let html = base_template(&page).unwrap().0;
+		assert_eq!(html.matches("aria-current").count(), 1);
+		assert!(
+			html.contains(r#"<a href="/blog.html" aria-current="page">blog</a>"#)
+		);
+		assert!(html.contains(r#"<a href="/about.html">about</a>"#));
+		assert!(!base_template(&builder).unwrap().0.contains("aria-current"));
+
+		let current = nav_vars(&page.nav, page.active.as_deref())
+			.try_iter()
+			.unwrap()
+			.map(|link| link.get_attr("current").unwrap().is_true())
+			.collect::<Vec<_>>();
+		assert_eq!(current, [true, false, false]);
+	}
+}
@@ -0,0 +1,196 @@
+use std::path::{Path, PathBuf};
+
+use maud::{html, Markup, PreEscaped};
+
+use crate::{
+	error::Error,
+	page_builder::{NavLink, PageBuilder},
+	parsers::frontmatter::{Frontmatter, Kind},
+	post::MarkdownRenderer,
+	util::files_in,
+	Result,
+};
+
+/// Paths the site's own pages are written to, which a page in `pages/` would
+/// overwrite.
+const RESERVED: &[&str] = &[
+	"index", "blog", "tags", "archive", "404", "posts", "tag", "series",
+	"static", "images",
+];
+
+/// A page that isn't a blog post, from a markdown file in `pages/`. It keeps
+/// its path, so `pages/about.md` is at `/about.html`. Pages aren't in the blog
+/// list or the feed.
+pub struct Page {
+	pub frontmatter: Frontmatter,
+	pub href: String,
+	pub content: Markup,
+}
+
+impl Page {
+	pub fn nav_link(&self) -> Option<NavLink> {
+		self.frontmatter.nav.then(|| NavLink {
+			label: self.frontmatter.title.to_lowercase(),
+			href: self.href.clone(),
+			external: false,
+			icon: None,
+		})
+	}
+
+	pub fn render(&self) -> Markup {
+		html! {
+			h1.post-title { (self.frontmatter.title) }
+			@if let Some(description) = &self.frontmatter.description {
+				.post-description {
+					(description)
+				}
+			}
+			hr;
+			article.markdown.sidenotes[self.frontmatter.sidenotes] {
+				(self.content)
+			}
+		}
+	}
+}
+
+/// Read and render every page in `dir`, which doesn't have to exist.
+pub fn read_all_pages<P: AsRef<Path>>(
+	dir: P,
+	renderer: &MarkdownRenderer,
+) -> Result<Vec<Page>> {
+	let dir = dir.as_ref();
+	if !dir.is_dir() {
+		return Ok(Vec::new());
+	}
+
+	let mut pages = Vec::new();
+	let mut unresolved_links = Vec::new();
+	for path in files_in(dir)? {
+		let err = |reason: String| Error::InvalidPostFile {
+			path: path.clone(),
+			reason,
+		};
+		let name =
+			page_name(path.strip_prefix(dir).unwrap_or(&path)).map_err(err)?;
+
+		let raw = std::fs::read_to_string(&path)?;
+		let (content, frontmatter) =
+			Frontmatter::parse(&raw, Kind::Page).map_err(|error| Error::Parsing {
+				path: PathBuf::from(&path),
+				error,
+			})?;
+		let content_start = raw.len() - content.len();
+
+		let markdown =
+			renderer.render(&path, &raw, content_start, frontmatter.sidenotes)?;
+		unresolved_links.extend(
+			markdown
+				.unresolved_links
+				.into_iter()
+				.map(|link| (path.clone(), link)),
+		);
+		pages.push(Page {
+			frontmatter,
+			href: format!("/{}.html", name),
+			content: html! { (PreEscaped(markdown.html)) },
+		});
+	}
+
+	if !unresolved_links.is_empty() {
+		return Err(Error::UnresolvedLinks(unresolved_links));
+	}
+
+	Ok(pages)
+}
+
+/// `about` for `about.md`, as long as the site isn't using that path.
+fn page_name(path: &Path) -> std::result::Result<String, String> {
+	let name = path
+		.to_string_lossy()
+		.strip_suffix(".md")
+		.ok_or_else(|| "Page file must end in .md".to_string())?
+		.replace('\\', "/");
+	let first = name.split('/').next().unwrap_or_default();
+	if RESERVED.contains(&first) {
+		return Err(format!("/{}.html is taken by the site", name));
+	}
+	Ok(name)
+}
+
+/// The header links: pages with `nav` set, in order of their paths, and then
+/// the ones from `site.toml`.
+pub fn nav_links(pages: &[Page], site_nav: Vec<NavLink>) -> Vec<NavLink> {
+	pages
+		.iter()
+		.filter_map(|page| page.nav_link())
+		.chain(site_nav)
+		.collect()
+}
+
+pub fn gen_pages(builder: &PageBuilder, pages: &[Page]) -> Result<()> {
+	for page in pages {
+		let mut page_builder = builder
+			.clone()
+			.title(&format!("{} - john mcparland", page.frontmatter.title));
+		if let Some(description) = &page.frontmatter.description {
+			page_builder = page_builder.description(description);
+		}
+		page_builder
+			.body(page.render())
+			.write(page.href.trim_start_matches('/'))?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn page(name: &str, nav: bool) -> Page {
+		let raw = format!("---\ntitle: {}\nnav: {}\n---\n", name, nav);
+		Page {
+			frontmatter: Frontmatter::parse(&raw, Kind::Page).unwrap().1,
+			href: format!("/{}.html", name.to_lowercase()),
+			content: html! {},
+		}
+	}
+
+	#[test]
+	fn test_page_name() {
+		assert_eq!(page_name(Path::new("about.md")).unwrap(), "about");
+		assert_eq!(
+			page_name(Path::new("projects/bevy.md")).unwrap(),
+			"projects/bevy"
+		);
+		assert!(page_name(Path::new("about.txt")).is_err());
+		assert!(page_name(Path::new("blog.md")).is_err());
+		assert!(page_name(Path::new("images/cat.md")).is_err());
+		assert!(page_name(Path::new("tag/rust.md")).is_err());
+		// Only the first part of the path is reserved.
+		assert!(page_name(Path::new("notes/tag.md")).is_ok());
+	}
+
+	#[test]
+	fn test_nav_links() {
+		let pages = [
+			page("About", true),
+			page("Hidden", false),
+			page("Now", true),
+		];
+		let site = vec![NavLink {
+			label: "blog".to_string(),
+			href: "/blog.html".to_string(),
+			external: false,
+			icon: None,
+		}];
+		let links = nav_links(&pages, site);
+		assert_eq!(
+			links
+				.iter()
+				.map(|link| link.label.as_str())
+				.collect::<Vec<_>>(),
+			["about", "now", "blog"]
+		);
+		assert_eq!(links[0].href, "/about.html");
+	}
+}
@@ -0,0 +1,120 @@
+use maud::{html, Markup};
+
+/// One page of a list split up by [`paginate`]. The first page of `/blog` is
+/// at `/blog.html`, and the rest at `/blog/page/2.html` and so on.
+pub struct Page<'a, T> {
+	pub items: &'a [T],
+	/// Counting from 1.
+	pub number: usize,
+	pub count: usize,
+	base: &'a str,
+}
+
+/// Split `items` into pages of `size`, or one page of everything for a `size`
+/// of 0. There's always at least one page, even if it's empty.
+pub fn paginate<'a, T>(
+	items: &'a [T],
+	size: usize,
+	base: &'a str,
+) -> Vec<Page<'a, T>> {
+	let chunks = if size == 0 || items.is_empty() {
+		vec![items]
+	} else {
+		items.chunks(size).collect()
+	};
+	let count = chunks.len();
+	chunks
+		.into_iter()
+		.enumerate()
+		.map(|(i, items)| Page {
+			items,
+			number: i + 1,
+			count,
+			base,
+		})
+		.collect()
+}
+
+impl<T> Page<'_, T> {
+	pub fn href(&self, number: usize) -> String {
+		if number == 1 {
+			format!("{}.html", self.base)
+		} else {
+			format!("{}/page/{}.html", self.base, number)
+		}
+	}
+
+	/// Where the page is written, relative to the output directory.
+	pub fn path(&self) -> String {
+		self.href(self.number).trim_start_matches('/').to_string()
+	}
+
+	pub fn prev(&self) -> Option<String> {
+		(self.number > 1).then(|| self.href(self.number - 1))
+	}
+
+	pub fn next(&self) -> Option<String> {
+		(self.number < self.count).then(|| self.href(self.number + 1))
+	}
+
+	/// `rel="prev"` and `rel="next"` links for the page's `<head>`.
+	pub fn head(&self) -> Markup {
+		html! {
+			@if let Some(prev) = self.prev() {
+				link rel="prev" href=(prev);
+			}
+			@if let Some(next) = self.next() {
+				link rel="next" href=(next);
+			}
+		}
+	}
+
+	/// Newer and older links with a link to every page between them, or
+	/// nothing if there's only one page.
+	pub fn nav(&self) -> Markup {
+		html! {
+			@if self.count > 1 {
+				nav.pagination aria-label="Pages" {
+					@if let Some(prev) = self.prev() {
+						a rel="prev" href=(prev) { "← Newer" }
+					}
+					@for number in 1..=self.count {
+						@if number == self.number {
+							span aria-current="page" { (number) }
+						} @else {
+							a href=(self.href(number)) { (number) }
+						}
+					}
+					@if let Some(next) = self.next() {
+						a rel="next" href=(next) { "Older →" }
+					}
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_paginate() {
+		let items = [1, 2, 3, 4, 5];
+		let pages = paginate(&items, 2, "/tag/rust");
+		assert_eq!(pages.len(), 3);
+		assert_eq!(pages[2].items, [5]);
+		assert_eq!(pages[0].path(), "tag/rust.html");
+		assert_eq!(pages[0].prev(), None);
+		assert_eq!(pages[0].next().as_deref(), Some("/tag/rust/page/2.html"));
+		assert_eq!(pages[1].prev().as_deref(), Some("/tag/rust.html"));
+		assert_eq!(pages[2].path(), "tag/rust/page/3.html");
+		assert_eq!(pages[2].next(), None);
+
+		assert_eq!(paginate(&items, 0, "/blog")[0].items.len(), 5);
+		assert_eq!(paginate(&items, 5, "/blog")[0].nav().0, "");
+		let empty = paginate(&items[..0], 2, "/blog");
+		assert_eq!(empty.len(), 1);
+		assert!(empty[0].items.is_empty());
+	}
+}
@@ -22,8 +22,8 @@ pub fn many1<'a, T, Tf: Fn(&'a str) -> PResult<'a, T>>(
 	let parser = many0(f);
 	move |input: &str| {
 		let (res, input) = parser(input)?;
-		if res.len() == 0 {
-			Err(ParserError::Detailed(format!("many1 got 0 items")))
+		if res.is_empty() {
+			Err(ParserError::Detailed("many1 got 0 items".to_string()))
 		} else {
 			Ok((res, input))
 		}
@@ -44,9 +44,9 @@ pub fn many0<'a, T, Tf: Fn(&'a str) -> PResult<'a, T>>(
 			match f(input) {
 				Ok((v, input_after)) => {
 					if input.len() <= input_after.len() {
-						return Err(ParserError::Detailed(format!(
-							"many0 didn't progress"
-						)));
+						return Err(ParserError::Detailed(
+							"many0 didn't progress".to_string(),
+						));
 					}
 					res.push(v);
 					offset += input.len() - input_after.len();
@@ -60,12 +60,11 @@ pub fn many0<'a, T, Tf: Fn(&'a str) -> PResult<'a, T>>(
 	}
 }
 
-pub fn alpha<'a>(input: &'a str) -> PResult<&'a str> {
+pub fn alpha(input: &str) -> PResult<'_, &str> {
 	in_set(ALPHA)(input)
 }
 
-const ALPHA: &'static str =
-	"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const ALPHA: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
 pub fn not_in_set<'a>(
 	set: &'static str,
@@ -192,7 +191,7 @@ pub fn ws<'a>(input: &'a str) -> PResult<'a, &'a str> {
 			return Ok(input.split_at(i));
 		}
 	}
-	Ok(("", input))
+	Ok((input, ""))
 }
 
 pub fn map<'a, I, If: Fn(&'a str) -> PResult<'a, I>, O, Of: Fn(I) -> O>(
@@ -210,7 +209,7 @@ pub fn rest<'a>(input: &'a str) -> PResult<'a, &'a str> {
 	Ok((input, ""))
 }
 
-type PResult<'a, T> = Result<(T, &'a str), ParserError>;
+pub type PResult<'a, T> = Result<(T, &'a str), ParserError>;
 
 #[derive(Debug)]
 pub enum ParserError {
@@ -255,6 +254,15 @@ mod test {
 		assert_eq!(input, "123?!");
 	}
 
+	#[test]
+	fn test_ws() {
+		assert_eq!(ws(" \t\nwords ").unwrap(), (" \t\n", "words "));
+		assert_eq!(ws("words ").unwrap(), ("", "words "));
+		// Input that's all whitespace is all consumed.
+		assert_eq!(ws("  \n").unwrap(), ("  \n", ""));
+		assert_eq!(ws("").unwrap(), ("", ""));
+	}
+
 	#[test]
 	fn test_map() {
 		let parse_bool = map(alpha, |s| match s {
@@ -298,6 +306,8 @@ mod test {
 			let (title, input) = until("\n")(input)?;
 			Ok((title, input))
 		}
+		assert_eq!(title_parser("# Title\nbody").unwrap(), (" Title", "body"));
+		assert!(title_parser("Title\nbody").is_err());
 	}
 
 	#[test]
@@ -19,7 +19,7 @@ impl Date {
 
 		let month = month.parse().map_err(|_| err())?;
 
-		if month < 1 || month > 12 {
+		if !(1..=12).contains(&month) {
 			return Err(err());
 		}
 
@@ -30,6 +30,25 @@ impl Date {
 		})
 	}
 
+	/// Days from the start of 1970, going by the proleptic Gregorian calendar.
+	pub fn days_since_epoch(&self) -> i64 {
+		// https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+		let (month, day) = (self.month as i64, self.day as i64);
+		let year = self.year as i64 - (month <= 2) as i64;
+		let era = year.div_euclid(400);
+		let year_of_era = year - era * 400;
+		let day_of_year =
+			(153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+		let day_of_era =
+			year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+		era * 146097 + day_of_era - 719468
+	}
+
+	/// How many days apart two dates are, either way.
+	pub fn days_between(&self, other: &Date) -> u64 {
+		self.days_since_epoch().abs_diff(other.days_since_epoch())
+	}
+
 	pub fn pretty_no_day(&self) -> String {
 		format!("{} {}", self.month_name(), self.year)
 	}
@@ -67,3 +86,19 @@ impl Date {
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_days_since_epoch() {
+		assert_eq!(Date::new(1970, 1, 1).days_since_epoch(), 0);
+		assert_eq!(Date::new(2000, 3, 1).days_since_epoch(), 11017);
+		assert_eq!(Date::new(2024, 2, 29).days_since_epoch(), 19782);
+		assert_eq!(
+			Date::new(2023, 12, 31).days_between(&Date::new(2024, 1, 1)),
+			1
+		);
+	}
+}
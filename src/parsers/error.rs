@@ -3,4 +3,11 @@ pub enum ParsingError {
 	InvalidDate(String),
 	CannotFindFrontmatter,
 	InvalidFrontmatter(String),
+	UnknownShortcode { name: String, line: usize },
+	Shortcode { line: usize, reason: String },
+	InvalidMath { line: usize, reason: String },
+	InvalidToml { line: usize, reason: String },
+	InvalidTagConfig(String),
+	InvalidSiteConfig(String),
+	InvalidTemplate { line: usize, reason: String },
 }
@@ -1,15 +1,58 @@
 use super::error::ParsingError;
+use crate::{series, util::slugify_path};
+
+/// Whether frontmatter is from a post or a page in `pages/`, which each have
+/// keys the other doesn't.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+	Post,
+	Page,
+}
+
+const POST_ONLY: [&str; 7] = [
+	"tags",
+	"series",
+	"series_order",
+	"layout",
+	"css",
+	"js",
+	"head",
+];
+const PAGE_ONLY: [&str; 1] = ["nav"];
 
 pub struct Frontmatter {
 	pub title: String,
-	pub description: String,
+	/// Posts without one are summarized by their excerpt instead.
+	pub description: Option<String>,
+	/// Optional, and only for posts.
 	pub tags: Vec<String>,
+	/// Show footnotes in the margin next to where they're referenced, on
+	/// screens wide enough for it.
+	pub sidenotes: bool,
+	/// Name of a series of posts this one is part of.
+	pub series: Option<String>,
+	/// Where in its series the post goes. Posts without one come after those
+	/// with, by date.
+	pub series_order: Option<u32>,
+	/// Link to the page in the site header. Only for pages in `pages/`.
+	pub nav: bool,
+	/// A template in `templates/` to render the post with instead of `post`.
+	/// Only for posts, like `css`, `js`, `head`, `tags` and the `series` keys.
+	pub layout: Option<String>,
+	/// Stylesheets, scripts and a file of html for the `<head>`, from the
+	/// post's folder in `static/embeds/`.
+	pub css: Vec<String>,
+	pub js: Vec<String>,
+	pub head: Option<String>,
 }
 
 impl Frontmatter {
-	pub fn parse<'a>(
-		post: &'a str,
-	) -> Result<(&'a str, Frontmatter), ParsingError> {
+	/// Keys that are only for the other `kind` of file are errors, since
+	/// they'd be ignored.
+	pub fn parse(
+		post: &str,
+		kind: Kind,
+	) -> Result<(&str, Frontmatter), ParsingError> {
 		let (frontmatter, post) = post
 			.strip_prefix("---")
 			.ok_or(ParsingError::CannotFindFrontmatter)?
@@ -18,36 +61,161 @@ impl Frontmatter {
 
 		let err = || ParsingError::InvalidFrontmatter(frontmatter.to_string());
 
-		let mut lines = frontmatter.trim().lines();
+		let mut lines = frontmatter.trim().lines().peekable();
 
 		let title = parse_line(lines.next().ok_or_else(err)?, "title")?.to_string();
-		let description =
-			parse_line(lines.next().ok_or_else(err)?, "description")?.to_string();
-		let tags = parse_tags(parse_line(lines.next().ok_or_else(err)?, "tags")?)?;
-
-		Ok((
-			post,
-			Frontmatter {
-				title,
-				description,
-				tags,
-			},
-		))
+		let description = match lines.next_if(|line| {
+			line
+				.split_once(":")
+				.is_some_and(|(key, _)| key.trim() == "description")
+		}) {
+			Some(line) => Some(parse_line(line, "description")?.to_string()),
+			None => None,
+		};
+		let tags = match lines.next_if(|line| {
+			line
+				.split_once(":")
+				.is_some_and(|(key, _)| key.trim() == "tags")
+		}) {
+			Some(line) => {
+				check_kind("tags", kind)?;
+				parse_tags(parse_line(line, "tags")?)?
+			}
+			None => Vec::new(),
+		};
+
+		let mut frontmatter = Frontmatter {
+			title,
+			description,
+			tags,
+			sidenotes: false,
+			series: None,
+			series_order: None,
+			nav: false,
+			layout: None,
+			css: Vec::new(),
+			js: Vec::new(),
+			head: None,
+		};
+
+		// Anything after the required keys is optional, in any order.
+		for line in lines {
+			let (key, value) = line
+				.split_once(":")
+				.ok_or_else(|| ParsingError::InvalidFrontmatter(line.to_string()))?;
+			let value = value.trim();
+			check_kind(key.trim(), kind)?;
+			match key.trim() {
+				"sidenotes" => frontmatter.sidenotes = parse_bool(value)?,
+				"nav" => frontmatter.nav = parse_bool(value)?,
+				"layout" => frontmatter.layout = Some(value.to_string()),
+				"css" => frontmatter.css = parse_files(value)?,
+				"js" => frontmatter.js = parse_files(value)?,
+				"head" => frontmatter.head = Some(value.to_string()),
+				"series" => {
+					if series::slug(value).is_empty() {
+						return Err(ParsingError::InvalidFrontmatter(format!(
+							"empty series in {}",
+							line
+						)));
+					}
+					frontmatter.series = Some(value.to_string())
+				}
+				"series_order" => {
+					frontmatter.series_order = Some(value.parse().map_err(|_| {
+						ParsingError::InvalidFrontmatter(format!(
+							"{} should be a number",
+							value
+						))
+					})?)
+				}
+				key => {
+					return Err(ParsingError::InvalidFrontmatter(format!(
+						"unknown key {}",
+						key
+					)))
+				}
+			}
+		}
+
+		Ok((post, frontmatter))
 	}
 }
 
+fn check_kind(key: &str, kind: Kind) -> Result<(), ParsingError> {
+	let only_for = if POST_ONLY.contains(&key) {
+		Kind::Post
+	} else if PAGE_ONLY.contains(&key) {
+		Kind::Page
+	} else {
+		return Ok(());
+	};
+	if only_for == kind {
+		return Ok(());
+	}
+	Err(ParsingError::InvalidFrontmatter(format!(
+		"{} is only for {}",
+		key,
+		match only_for {
+			Kind::Post => "posts",
+			Kind::Page => "pages",
+		}
+	)))
+}
+
 fn parse_tags(tags: &str) -> Result<Vec<String>, ParsingError> {
 	let err = || ParsingError::InvalidFrontmatter(tags.to_string());
-	Ok(
-		tags
-			.strip_prefix("[")
-			.ok_or_else(err)?
-			.strip_suffix("]")
-			.ok_or_else(err)?
-			.split(",")
-			.map(|s| s.trim().to_string())
-			.collect::<Vec<String>>(),
-	)
+	let inner = tags
+		.strip_prefix("[")
+		.ok_or_else(err)?
+		.strip_suffix("]")
+		.ok_or_else(err)?;
+	if inner.trim().is_empty() {
+		return Ok(Vec::new());
+	}
+
+	let mut parsed: Vec<String> = Vec::new();
+	for tag in inner.split(",") {
+		let tag = tag.trim();
+		if slugify_path(tag).is_empty() {
+			return Err(ParsingError::InvalidFrontmatter(format!(
+				"empty tag in {}",
+				tags
+			)));
+		}
+		// The same tag twice with different capitalisation is still the same tag.
+		if !parsed.iter().any(|t| slugify_path(t) == slugify_path(tag)) {
+			parsed.push(tag.to_string());
+		}
+	}
+	Ok(parsed)
+}
+
+/// One file, or a list like `[a.css, b.css]`.
+fn parse_files(value: &str) -> Result<Vec<String>, ParsingError> {
+	let files = match value.strip_prefix('[') {
+		Some(inner) => inner
+			.strip_suffix(']')
+			.ok_or_else(|| ParsingError::InvalidFrontmatter(value.to_string()))?
+			.split(',')
+			.map(str::trim)
+			.filter(|file| !file.is_empty())
+			.map(str::to_string)
+			.collect(),
+		None => vec![value.to_string()],
+	};
+	Ok(files)
+}
+
+fn parse_bool(value: &str) -> Result<bool, ParsingError> {
+	match value {
+		"true" => Ok(true),
+		"false" => Ok(false),
+		value => Err(ParsingError::InvalidFrontmatter(format!(
+			"{} should be true or false",
+			value
+		))),
+	}
 }
 
 fn parse_line<'a>(
@@ -68,3 +236,89 @@ fn parse_line<'a>(
 
 	Ok(value.trim())
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn parse(keys: &str) -> Result<Frontmatter, ParsingError> {
+		Frontmatter::parse(&format!("---\ntitle: a\n{}\n---\n", keys), Kind::Post)
+			.map(|(_, frontmatter)| frontmatter)
+	}
+
+	#[test]
+	fn test_tags() {
+		let tags = |line: &str| parse(line).map(|frontmatter| frontmatter.tags);
+		assert_eq!(tags("tags: [Rust, web]").unwrap(), ["Rust", "web"]);
+		assert!(tags("tags: []").unwrap().is_empty());
+		assert!(tags("tags: [ ]").unwrap().is_empty());
+		assert!(tags("").unwrap().is_empty());
+		// The first spelling of a tag is kept.
+		assert_eq!(
+			tags("tags: [Rust, web, rust, RUST , Web]").unwrap(),
+			["Rust", "web"]
+		);
+		for bad in ["tags: [a, ]", "tags: [a, , b]", "tags: [???]", "tags: a"] {
+			assert!(
+				matches!(tags(bad), Err(ParsingError::InvalidFrontmatter(_))),
+				"{}",
+				bad
+			);
+		}
+	}
+
+	#[test]
+	fn test_files() {
+		let frontmatter =
+			parse("layout: wide\ncss: [a.css, b.css]\njs: c.js\nhead: d.html")
+				.unwrap();
+		assert_eq!(frontmatter.layout.as_deref(), Some("wide"));
+		assert_eq!(frontmatter.css, ["a.css", "b.css"]);
+		assert_eq!(frontmatter.js, ["c.js"]);
+		assert_eq!(frontmatter.head.as_deref(), Some("d.html"));
+		assert!(parse("css: []").unwrap().css.is_empty());
+		assert_eq!(parse("js: [ a.js , ]").unwrap().js, ["a.js"]);
+		assert!(parse("css: [a.css").is_err());
+	}
+
+	#[test]
+	fn test_series() {
+		let frontmatter = parse("series: Bevy Camera\nseries_order: 2").unwrap();
+		assert_eq!(frontmatter.series.as_deref(), Some("Bevy Camera"));
+		assert_eq!(frontmatter.series_order, Some(2));
+		assert!(parse("series: ???").is_err());
+		assert!(parse("series: _").is_err());
+		assert!(parse("series_order: first").is_err());
+	}
+
+	#[test]
+	fn test_kinds() {
+		let parse = |keys: &str, kind: Kind| {
+			Frontmatter::parse(&format!("---\ntitle: a\n{}\n---\n", keys), kind)
+				.map(|(_, frontmatter)| frontmatter)
+		};
+		let only_for = |keys: &str, kind: Kind| match parse(keys, kind) {
+			Err(ParsingError::InvalidFrontmatter(reason)) => reason,
+			_ => panic!("{} should be an error", keys),
+		};
+		for keys in [
+			"tags: []",
+			"tags: [a]",
+			"series: a",
+			"series_order: 1",
+			"layout: wide",
+			"css: []",
+			"js: a.js",
+			"head: a.html",
+			"description: a\ntags: [a]",
+		] {
+			assert!(parse(keys, Kind::Post).is_ok(), "{}", keys);
+			assert!(only_for(keys, Kind::Page).ends_with("is only for posts"));
+		}
+		assert!(parse("nav: false", Kind::Page).is_ok());
+		assert_eq!(only_for("nav: false", Kind::Post), "nav is only for pages");
+		for kind in [Kind::Post, Kind::Page] {
+			assert!(parse("description: a\nsidenotes: true", kind).is_ok());
+		}
+	}
+}
@@ -0,0 +1,589 @@
+use maud::{html, Markup};
+
+/// Render a LaTeX math expression as MathML, so it can be displayed by the
+/// browser without any javascript. Only the commonly used subset of LaTeX is
+/// supported, anything else is an error rather than being silently dropped.
+pub fn latex_to_mathml(tex: &str, display: bool) -> Result<Markup, String> {
+	let tokens = tokenize(tex)?;
+	let mut parser = Parser {
+		tokens,
+		pos: 0,
+		display,
+	};
+	let body = parser.expr()?;
+	if let Some(token) = parser.peek() {
+		return Err(format!("unexpected {}", token.describe()));
+	}
+
+	Ok(html! {
+		math xmlns="http://www.w3.org/1998/Math/MathML" display=[display.then_some("block")] {
+			semantics {
+				mrow { (body) }
+				annotation encoding="application/x-tex" { (tex.trim()) }
+			}
+		}
+	})
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Token<'a> {
+	/// A command without its leading backslash, `\frac` is `Command("frac")`.
+	Command(&'a str),
+	Char(char),
+	Open,
+	Close,
+	Sup,
+	Sub,
+	Align,
+}
+
+impl<'a> Token<'a> {
+	fn describe(&self) -> String {
+		match self {
+			Token::Command(name) => format!("\\{}", name),
+			Token::Char(c) => format!("\"{}\"", c),
+			Token::Open => "\"{\"".to_string(),
+			Token::Close => "\"}\"".to_string(),
+			Token::Sup => "\"^\"".to_string(),
+			Token::Sub => "\"_\"".to_string(),
+			Token::Align => "\"&\"".to_string(),
+		}
+	}
+}
+
+fn tokenize(tex: &str) -> Result<Vec<Token<'_>>, String> {
+	let mut tokens = Vec::new();
+	let mut chars = tex.char_indices().peekable();
+
+	while let Some((i, c)) = chars.next() {
+		let token = match c {
+			'\\' => {
+				let start = i + 1;
+				let mut end = start;
+				while let Some((j, c)) = chars.peek() {
+					if c.is_ascii_alphabetic() {
+						end = j + c.len_utf8();
+						chars.next();
+					} else {
+						break;
+					}
+				}
+				if end == start {
+					// Single character commands like `\{` or `\,`
+					let (_, c) =
+						chars.next().ok_or_else(|| "trailing \"\\\"".to_string())?;
+					end = start + c.len_utf8();
+				}
+				Token::Command(&tex[start..end])
+			}
+			'{' => Token::Open,
+			'}' => Token::Close,
+			'^' => Token::Sup,
+			'_' => Token::Sub,
+			'&' => Token::Align,
+			'%' => return Err("comments aren't supported in math".to_string()),
+			c if c.is_whitespace() => continue,
+			c => Token::Char(c),
+		};
+		tokens.push(token);
+	}
+
+	Ok(tokens)
+}
+
+struct Parser<'a> {
+	tokens: Vec<Token<'a>>,
+	pos: usize,
+	display: bool,
+}
+
+impl<'a> Parser<'a> {
+	fn peek(&self) -> Option<Token<'a>> {
+		self.tokens.get(self.pos).copied()
+	}
+
+	fn next(&mut self) -> Option<Token<'a>> {
+		let token = self.peek();
+		self.pos += 1;
+		token
+	}
+
+	fn expect(&mut self, expected: Token) -> Result<(), String> {
+		match self.next() {
+			Some(token) if token == expected => Ok(()),
+			Some(token) => Err(format!(
+				"expected {} but found {}",
+				expected.describe(),
+				token.describe()
+			)),
+			None => Err(format!("expected {}", expected.describe())),
+		}
+	}
+
+	/// A sequence of terms, up to the end of the current group, row or
+	/// cell.
+	fn expr(&mut self) -> Result<Markup, String> {
+		let mut items = Vec::new();
+		while let Some(token) = self.peek() {
+			match token {
+				Token::Close | Token::Align => break,
+				Token::Command("right" | "end" | "\\") => break,
+				_ => items.push(self.term()?),
+			}
+		}
+		Ok(html! { @for item in items { (item) } })
+	}
+
+	/// An atom and its super/subscripts.
+	fn term(&mut self) -> Result<Markup, String> {
+		let big = matches!(
+			self.peek(),
+			Some(Token::Command("sum" | "prod" | "lim" | "bigcup" | "bigcap"))
+		);
+		let base = match self.peek() {
+			Some(Token::Sup | Token::Sub) => html! { mrow {} },
+			_ => self.atom()?,
+		};
+
+		let mut sub = None;
+		let mut sup = None;
+		loop {
+			match self.peek() {
+				Some(Token::Sub) if sub.is_none() => {
+					self.next();
+					sub = Some(self.script("subscript")?);
+				}
+				Some(Token::Sup) if sup.is_none() => {
+					self.next();
+					sup = Some(self.script("superscript")?);
+				}
+				Some(Token::Char('\'')) if sup.is_none() => {
+					self.next();
+					sup = Some(html! { mo { "′" } });
+				}
+				_ => break,
+			}
+		}
+
+		let under_over = big && self.display;
+		Ok(match (sub, sup) {
+			(None, None) => base,
+			(Some(sub), None) if under_over => html! { munder { (base) (sub) } },
+			(Some(sub), None) => html! { msub { (base) (sub) } },
+			(None, Some(sup)) if under_over => html! { mover { (base) (sup) } },
+			(None, Some(sup)) => html! { msup { (base) (sup) } },
+			(Some(sub), Some(sup)) if under_over => {
+				html! { munderover { (base) (sub) (sup) } }
+			}
+			(Some(sub), Some(sup)) => html! { msubsup { (base) (sub) (sup) } },
+		})
+	}
+
+	fn script(&mut self, what: &str) -> Result<Markup, String> {
+		match self.peek() {
+			None | Some(Token::Close | Token::Sup | Token::Sub | Token::Align) => {
+				Err(format!("missing {}", what))
+			}
+			_ => self.atom(),
+		}
+	}
+
+	/// The argument to a command, either a `{group}` or a single token.
+	fn group(&mut self) -> Result<Markup, String> {
+		match self.peek() {
+			None | Some(Token::Close) => Err("missing argument".to_string()),
+			Some(_) => self.atom(),
+		}
+	}
+
+	/// The raw text of a `{group}`, for commands like `\text`.
+	fn text_group(&mut self) -> Result<String, String> {
+		self.expect(Token::Open)?;
+		let mut text = String::new();
+		loop {
+			match self.next() {
+				Some(Token::Close) => break,
+				Some(Token::Char(c)) => text.push(c),
+				Some(Token::Command(" ")) => text.push(' '),
+				Some(Token::Command(c)) if c.len() == 1 => text.push_str(c),
+				Some(token) => {
+					return Err(format!("unexpected {} in text", token.describe()))
+				}
+				None => return Err("missing \"}\"".to_string()),
+			}
+		}
+		Ok(text)
+	}
+
+	fn atom(&mut self) -> Result<Markup, String> {
+		let token = self.next().ok_or_else(|| "unexpected end".to_string())?;
+		match token {
+			Token::Open => {
+				let inner = self.expr()?;
+				self.expect(Token::Close)?;
+				Ok(html! { mrow { (inner) } })
+			}
+			Token::Char(c) if c.is_ascii_digit() || c == '.' => {
+				let mut number = c.to_string();
+				while let Some(Token::Char(c)) = self.peek() {
+					if !(c.is_ascii_digit() || c == '.') {
+						break;
+					}
+					number.push(c);
+					self.next();
+				}
+				Ok(html! { mn { (number) } })
+			}
+			Token::Char(c) if c.is_alphabetic() => Ok(html! { mi { (c) } }),
+			Token::Char(c) => Ok(html! { mo { (c) } }),
+			Token::Command(name) => self.command(name),
+			token => Err(format!("unexpected {}", token.describe())),
+		}
+	}
+
+	fn command(&mut self, name: &'a str) -> Result<Markup, String> {
+		if let Some(letter) = letter(name) {
+			return Ok(html! { mi { (letter) } });
+		}
+		if let Some(op) = operator(name) {
+			return Ok(html! { mo { (op) } });
+		}
+		if FUNCTIONS.contains(&name) {
+			return Ok(html! { mi { (name) } });
+		}
+
+		Ok(match name {
+			"frac" | "dfrac" | "tfrac" => {
+				let numerator = self.group()?;
+				let denominator = self.group()?;
+				html! { mfrac { (numerator) (denominator) } }
+			}
+			"binom" => {
+				let n = self.group()?;
+				let k = self.group()?;
+				html! {
+					mrow {
+						mo { "(" }
+						mfrac linethickness="0" { (n) (k) }
+						mo { ")" }
+					}
+				}
+			}
+			"sqrt" => {
+				if self.peek() == Some(Token::Char('[')) {
+					self.next();
+					let mut index = Vec::new();
+					while self.peek() != Some(Token::Char(']')) {
+						if self.peek().is_none() {
+							return Err("missing \"]\"".to_string());
+						}
+						index.push(self.term()?);
+					}
+					self.next();
+					let radicand = self.group()?;
+					html! { mroot { (radicand) mrow { @for i in index { (i) } } } }
+				} else {
+					let radicand = self.group()?;
+					html! { msqrt { (radicand) } }
+				}
+			}
+			"text" | "textrm" | "mathrm" | "operatorname" => {
+				let text = self.text_group()?;
+				if name == "text" || name == "textrm" {
+					html! { mtext { (text) } }
+				} else {
+					html! { mi mathvariant="normal" { (text) } }
+				}
+			}
+			"mathbf" | "mathit" | "mathbb" | "mathcal" | "mathsf" | "mathtt" => {
+				let text = self.text_group()?;
+				let variant = match name {
+					"mathbf" => "bold",
+					"mathit" => "italic",
+					"mathbb" => "double-struck",
+					"mathcal" => "script",
+					"mathsf" => "sans-serif",
+					_ => "monospace",
+				};
+				html! { mi mathvariant=(variant) { (text) } }
+			}
+			"hat" | "bar" | "vec" | "dot" | "ddot" | "tilde" | "overline" => {
+				let accent = match name {
+					"hat" => "^",
+					"bar" | "overline" => "¯",
+					"vec" => "→",
+					"dot" => "˙",
+					"ddot" => "¨",
+					_ => "~",
+				};
+				let base = self.group()?;
+				html! { mover accent="true" { (base) mo { (accent) } } }
+			}
+			"left" => {
+				let open = self.delimiter()?;
+				let inner = self.expr()?;
+				self.expect(Token::Command("right"))?;
+				let close = self.delimiter()?;
+				html! {
+					mrow {
+						mo stretchy="true" { (open) }
+						(inner)
+						mo stretchy="true" { (close) }
+					}
+				}
+			}
+			"begin" => self.environment()?,
+			"," | ":" | ";" | " " | "quad" | "qquad" | "!" => {
+				let width = match name {
+					"," => "0.1667em",
+					":" => "0.2222em",
+					";" => "0.2778em",
+					" " => "0.25em",
+					"quad" => "1em",
+					"qquad" => "2em",
+					_ => "-0.1667em",
+				};
+				html! { mspace width=(width) {} }
+			}
+			"{" | "}" | "|" | "$" | "#" | "&" | "_" | "%" => {
+				html! { mo { (if name == "|" { "‖" } else { name }) } }
+			}
+			name => return Err(format!("unknown command \\{}", name)),
+		})
+	}
+
+	/// The delimiter after a `\left` or `\right`. `.` is an invisible one.
+	fn delimiter(&mut self) -> Result<&'static str, String> {
+		Ok(match self.next() {
+			Some(Token::Char('(')) => "(",
+			Some(Token::Char(')')) => ")",
+			Some(Token::Char('[')) => "[",
+			Some(Token::Char(']')) => "]",
+			Some(Token::Char('|')) => "|",
+			Some(Token::Char('.')) => "",
+			Some(Token::Command("{")) => "{",
+			Some(Token::Command("}")) => "}",
+			Some(Token::Command("|")) => "‖",
+			Some(Token::Command("langle")) => "⟨",
+			Some(Token::Command("rangle")) => "⟩",
+			Some(token) => {
+				return Err(format!("invalid delimiter {}", token.describe()))
+			}
+			None => return Err("missing delimiter".to_string()),
+		})
+	}
+
+	/// `\begin{matrix} a & b \\ c & d \end{matrix}` and friends.
+	fn environment(&mut self) -> Result<Markup, String> {
+		let name = self.text_group()?;
+		let (open, close, align) = match name.as_str() {
+			"matrix" => ("", "", "center"),
+			"pmatrix" => ("(", ")", "center"),
+			"bmatrix" => ("[", "]", "center"),
+			"vmatrix" => ("|", "|", "center"),
+			"cases" => ("{", "", "left"),
+			"aligned" | "align" | "align*" => ("", "", "right left"),
+			name => return Err(format!("unknown environment \"{}\"", name)),
+		};
+
+		let mut rows = vec![vec![self.expr()?]];
+		loop {
+			match self.next() {
+				Some(Token::Align) => rows.last_mut().unwrap().push(self.expr()?),
+				Some(Token::Command("\\")) => rows.push(vec![self.expr()?]),
+				Some(Token::Command("end")) => break,
+				Some(token) => {
+					return Err(format!("unexpected {} in {}", token.describe(), name))
+				}
+				None => return Err(format!("missing \\end{{{}}}", name)),
+			}
+		}
+		let end = self.text_group()?;
+		if end != name {
+			return Err(format!("\\begin{{{}}} ended by \\end{{{}}}", name, end));
+		}
+
+		Ok(html! {
+			mrow {
+				@if !open.is_empty() { mo stretchy="true" { (open) } }
+				mtable columnalign=(align) {
+					@for row in rows {
+						mtr {
+							@for cell in row {
+								mtd { (cell) }
+							}
+						}
+					}
+				}
+				@if !close.is_empty() { mo stretchy="true" { (close) } }
+			}
+		})
+	}
+}
+
+const FUNCTIONS: [&str; 22] = [
+	"sin", "cos", "tan", "sec", "csc", "cot", "arcsin", "arccos", "arctan",
+	"sinh", "cosh", "tanh", "log", "ln", "exp", "lim", "max", "min", "det",
+	"gcd", "deg", "arg",
+];
+
+fn letter(name: &str) -> Option<&'static str> {
+	Some(match name {
+		"alpha" => "α",
+		"beta" => "β",
+		"gamma" => "γ",
+		"delta" => "δ",
+		"epsilon" => "ϵ",
+		"varepsilon" => "ε",
+		"zeta" => "ζ",
+		"eta" => "η",
+		"theta" => "θ",
+		"vartheta" => "ϑ",
+		"iota" => "ι",
+		"kappa" => "κ",
+		"lambda" => "λ",
+		"mu" => "μ",
+		"nu" => "ν",
+		"xi" => "ξ",
+		"pi" => "π",
+		"rho" => "ρ",
+		"sigma" => "σ",
+		"tau" => "τ",
+		"upsilon" => "υ",
+		"phi" => "ϕ",
+		"varphi" => "φ",
+		"chi" => "χ",
+		"psi" => "ψ",
+		"omega" => "ω",
+		"Gamma" => "Γ",
+		"Delta" => "Δ",
+		"Theta" => "Θ",
+		"Lambda" => "Λ",
+		"Xi" => "Ξ",
+		"Pi" => "Π",
+		"Sigma" => "Σ",
+		"Phi" => "Φ",
+		"Psi" => "Ψ",
+		"Omega" => "Ω",
+		"infty" => "∞",
+		"partial" => "∂",
+		"nabla" => "∇",
+		"ell" => "ℓ",
+		"hbar" => "ℏ",
+		"emptyset" => "∅",
+		_ => return None,
+	})
+}
+
+fn operator(name: &str) -> Option<&'static str> {
+	Some(match name {
+		"times" => "×",
+		"cdot" => "⋅",
+		"div" => "÷",
+		"pm" => "±",
+		"mp" => "∓",
+		"ast" => "∗",
+		"circ" => "∘",
+		"leq" | "le" => "≤",
+		"geq" | "ge" => "≥",
+		"neq" | "ne" => "≠",
+		"approx" => "≈",
+		"equiv" => "≡",
+		"sim" => "∼",
+		"propto" => "∝",
+		"ll" => "≪",
+		"gg" => "≫",
+		"to" | "rightarrow" => "→",
+		"leftarrow" | "gets" => "←",
+		"Rightarrow" | "implies" => "⇒",
+		"Leftarrow" => "⇐",
+		"Leftrightarrow" | "iff" => "⇔",
+		"mapsto" => "↦",
+		"in" => "∈",
+		"notin" => "∉",
+		"ni" => "∋",
+		"subset" => "⊂",
+		"subseteq" => "⊆",
+		"supset" => "⊃",
+		"supseteq" => "⊇",
+		"cup" => "∪",
+		"cap" => "∩",
+		"setminus" => "∖",
+		"land" | "wedge" => "∧",
+		"lor" | "vee" => "∨",
+		"lnot" | "neg" => "¬",
+		"forall" => "∀",
+		"exists" => "∃",
+		"ldots" | "dots" => "…",
+		"cdots" => "⋯",
+		"vdots" => "⋮",
+		"ddots" => "⋱",
+		"sum" => "∑",
+		"prod" => "∏",
+		"int" => "∫",
+		"iint" => "∬",
+		"oint" => "∮",
+		"bigcup" => "⋃",
+		"bigcap" => "⋂",
+		"mid" => "∣",
+		"langle" => "⟨",
+		"rangle" => "⟩",
+		"lfloor" => "⌊",
+		"rfloor" => "⌋",
+		"lceil" => "⌈",
+		"rceil" => "⌉",
+		_ => return None,
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn render(tex: &str) -> String {
+		latex_to_mathml(tex, false).unwrap().0
+	}
+
+	#[test]
+	fn test_simple() {
+		let output = render("19\\times19=361");
+		assert!(output.contains(
+			"<mrow><mn>19</mn><mo>×</mo><mn>19</mn><mo>=</mo><mn>361</mn></mrow>"
+		));
+		assert!(output.contains(
+			"<annotation encoding=\"application/x-tex\">19\\times19=361</annotation>"
+		));
+	}
+
+	#[test]
+	fn test_scripts() {
+		assert!(render("x_i^2")
+			.contains("<msubsup><mi>x</mi><mi>i</mi><mn>2</mn></msubsup>"));
+		assert!(render("e^{i\\pi}")
+			.contains("<msup><mi>e</mi><mrow><mi>i</mi><mi>π</mi></mrow></msup>"));
+	}
+
+	#[test]
+	fn test_frac_sqrt() {
+		assert!(render("\\frac{a}{b}").contains(
+			"<mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac>"
+		));
+		assert!(render("\\sqrt[3]{x}").contains("<mroot>"));
+	}
+
+	#[test]
+	fn test_matrix() {
+		let output = render("\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix}");
+		assert_eq!(output.matches("<mtr>").count(), 2);
+		assert_eq!(output.matches("<mtd>").count(), 4);
+	}
+
+	#[test]
+	fn test_errors() {
+		latex_to_mathml("\\bingus", false).unwrap_err();
+		latex_to_mathml("{x", false).unwrap_err();
+		latex_to_mathml("x}", false).unwrap_err();
+		latex_to_mathml("x^", false).unwrap_err();
+		latex_to_mathml("\\begin{pmatrix} a \\end{bmatrix}", false).unwrap_err();
+	}
+}
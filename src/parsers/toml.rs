@@ -0,0 +1,314 @@
+use std::collections::{BTreeMap, HashSet};
+
+use super::error::ParsingError;
+
+/// The parts of TOML the site's config files need: `[tables]`, which can be
+/// nested like `[rust.bevy]`, `[[arrays]]` of tables, and `key = value`
+/// pairs where the value is a string or `true` or `false`. Anything else is
+/// an error, rather than being skipped over.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	String(String),
+	Boolean(bool),
+	/// Only made by `[[arrays]]`, so always of tables.
+	Array(Vec<Value>),
+	Table(Table),
+}
+
+pub type Table = BTreeMap<String, Value>;
+
+impl Value {
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::String(s) => Some(s),
+			_ => None,
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Value::Boolean(b) => Some(*b),
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&[Value]> {
+		match self {
+			Value::Array(a) => Some(a),
+			_ => None,
+		}
+	}
+
+	pub fn as_table(&self) -> Option<&Table> {
+		match self {
+			Value::Table(t) => Some(t),
+			_ => None,
+		}
+	}
+}
+
+pub fn parse(input: &str) -> Result<Table, ParsingError> {
+	let mut root = Table::new();
+	// Path to the table keys are currently being added to.
+	let mut current: Vec<String> = Vec::new();
+	// Tables that have had a `[header]`, which can only happen once.
+	let mut headers: HashSet<Vec<String>> = HashSet::new();
+
+	for (i, line) in input.lines().enumerate() {
+		let err = |reason: String| ParsingError::InvalidToml {
+			line: i + 1,
+			reason,
+		};
+
+		let mut cursor = Cursor { rest: line };
+		cursor.skip_ws();
+		if cursor.at_end() {
+			continue;
+		}
+
+		if let Some(rest) = cursor.rest.strip_prefix("[[") {
+			cursor.rest = rest;
+			let key = cursor.key().map_err(err)?;
+			cursor
+				.expect("]]")
+				.and_then(|_| cursor.end())
+				.map_err(err)?;
+			let array = root
+				.entry(key.clone())
+				.or_insert_with(|| Value::Array(Vec::new()));
+			match array {
+				Value::Array(array) => array.push(Value::Table(Table::new())),
+				_ => return Err(err(format!("{} isn't an array of tables", key))),
+			}
+			current = vec![key];
+		} else if let Some(rest) = cursor.rest.strip_prefix('[') {
+			cursor.rest = rest;
+			let path = cursor.key_path().map_err(err)?;
+			cursor.expect("]").and_then(|_| cursor.end()).map_err(err)?;
+			if !headers.insert(path.clone()) {
+				return Err(err(format!("[{}] is defined twice", path.join("."))));
+			}
+			table_at(&mut root, &path).map_err(err)?;
+			current = path;
+		} else {
+			let key = cursor.key().map_err(err)?;
+			cursor.expect("=").map_err(err)?;
+			let value = cursor.value().map_err(err)?;
+			cursor.end().map_err(err)?;
+
+			let table = table_at(&mut root, &current).map_err(err)?;
+			if table.contains_key(&key) {
+				return Err(err(format!("{} is defined twice", key)));
+			}
+			table.insert(key, value);
+		}
+	}
+
+	Ok(root)
+}
+
+/// Find the table at `path`, creating any that don't exist yet. For an array
+/// of tables, that's the last one in it.
+fn table_at<'a>(
+	table: &'a mut Table,
+	path: &[String],
+) -> Result<&'a mut Table, String> {
+	let mut table = table;
+	for key in path {
+		let value = table
+			.entry(key.clone())
+			.or_insert_with(|| Value::Table(Table::new()));
+		table = match value {
+			Value::Table(table) => table,
+			Value::Array(array) => match array.last_mut() {
+				Some(Value::Table(table)) => table,
+				_ => return Err(format!("{} isn't a table", key)),
+			},
+			_ => return Err(format!("{} isn't a table", key)),
+		};
+	}
+	Ok(table)
+}
+
+struct Cursor<'a> {
+	rest: &'a str,
+}
+
+impl Cursor<'_> {
+	fn skip_ws(&mut self) {
+		self.rest = self.rest.trim_start();
+		if self.rest.starts_with('#') {
+			self.rest = "";
+		}
+	}
+
+	fn at_end(&self) -> bool {
+		self.rest.is_empty()
+	}
+
+	fn end(&mut self) -> Result<(), String> {
+		self.skip_ws();
+		if self.at_end() {
+			Ok(())
+		} else {
+			Err(format!("unexpected {}", self.rest))
+		}
+	}
+
+	fn expect(&mut self, token: &str) -> Result<(), String> {
+		self.skip_ws();
+		match self.rest.strip_prefix(token) {
+			Some(rest) => {
+				self.rest = rest;
+				Ok(())
+			}
+			None => Err(format!("expected {}", token)),
+		}
+	}
+
+	/// `a.b."c d"`
+	fn key_path(&mut self) -> Result<Vec<String>, String> {
+		let mut path = vec![self.key()?];
+		loop {
+			self.skip_ws();
+			match self.rest.strip_prefix('.') {
+				Some(rest) => {
+					self.rest = rest;
+					path.push(self.key()?);
+				}
+				None => return Ok(path),
+			}
+		}
+	}
+
+	fn key(&mut self) -> Result<String, String> {
+		self.skip_ws();
+		if self.rest.starts_with('"') {
+			return self.string();
+		}
+		let len = self
+			.rest
+			.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+			.unwrap_or(self.rest.len());
+		if len == 0 {
+			return Err("expected a key".to_string());
+		}
+		let key = self.rest[..len].to_string();
+		self.rest = &self.rest[len..];
+		Ok(key)
+	}
+
+	fn value(&mut self) -> Result<Value, String> {
+		self.skip_ws();
+		if self.rest.starts_with('"') {
+			return Ok(Value::String(self.string()?));
+		}
+		let len = self
+			.rest
+			.find(|c: char| c.is_whitespace() || c == '#')
+			.unwrap_or(self.rest.len());
+		let value = match &self.rest[..len] {
+			"true" => Value::Boolean(true),
+			"false" => Value::Boolean(false),
+			_ => {
+				return Err(format!(
+					"{} should be a \"string\", true or false",
+					self.rest
+				))
+			}
+		};
+		self.rest = &self.rest[len..];
+		Ok(value)
+	}
+
+	fn string(&mut self) -> Result<String, String> {
+		let mut chars = self.rest.char_indices().skip(1);
+		let mut out = String::new();
+		while let Some((i, c)) = chars.next() {
+			match c {
+				'"' => {
+					self.rest = &self.rest[i + 1..];
+					return Ok(out);
+				}
+				'\\' => match chars.next().map(|(_, c)| c) {
+					Some('n') => out.push('\n'),
+					Some('t') => out.push('\t'),
+					Some('"') => out.push('"'),
+					Some('\\') => out.push('\\'),
+					Some(c) => return Err(format!("unknown escape \\{}", c)),
+					None => break,
+				},
+				c => out.push(c),
+			}
+		}
+		Err("unterminated string".to_string())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_parse() {
+		let table = parse(
+			r#"
+# A comment
+title = "mcpar.land" # after a value too
+draft = false
+
+[rust]
+name = "Rust"
+description = "Posts about \"Rust\""
+
+[rust.bevy]
+name = "Bevy"
+
+["game dev"]
+
+[[nav]]
+label = "blog"
+
+[[nav]]
+label = "rss"
+external = true
+"#,
+		)
+		.unwrap();
+
+		assert_eq!(table["title"].as_str(), Some("mcpar.land"));
+		assert_eq!(table["draft"].as_bool(), Some(false));
+		let rust = table["rust"].as_table().unwrap();
+		assert_eq!(rust["description"].as_str(), Some("Posts about \"Rust\""));
+		assert_eq!(
+			rust["bevy"].as_table().unwrap()["name"].as_str(),
+			Some("Bevy")
+		);
+		assert_eq!(table["game dev"], Value::Table(Table::new()));
+		let nav = table["nav"].as_array().unwrap();
+		assert_eq!(nav.len(), 2);
+		assert_eq!(nav[1].as_table().unwrap()["external"].as_bool(), Some(true));
+	}
+
+	#[test]
+	fn test_errors() {
+		let line = |input: &str| match parse(input) {
+			Err(ParsingError::InvalidToml { line, .. }) => line,
+			other => panic!("{:?}", other),
+		};
+		assert_eq!(line("a = \"1\"\na = \"2\""), 2);
+		assert_eq!(line("\n\nb = \"unterminated"), 3);
+		assert_eq!(line("[table\n"), 1);
+		assert_eq!(line("[a]\n[b]\n[a]"), 3);
+		assert_eq!(line("a = \"x\"\n[a]"), 2);
+		assert_eq!(line("[a]\n[[a]]"), 2);
+		assert_eq!(line("[[a.b]]"), 1);
+		assert_eq!(line("a.b = \"x\""), 1);
+		// Only strings and booleans are supported.
+		assert_eq!(line("c = what"), 1);
+		assert_eq!(line("c = 1"), 1);
+		assert_eq!(line("c = [\"a\"]"), 1);
+		assert_eq!(line("c = { a = \"b\" }"), 1);
+		assert_eq!(line("c = truest"), 1);
+	}
+}
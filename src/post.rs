@@ -1,41 +1,140 @@
 use highlight_pulldown::PulldownHighlighter;
 use maud::{html, Markup, PreEscaped};
-use pulldown_cmark::Options;
-use std::path::{Path, PathBuf};
+use pulldown_cmark::{Event, Options, Tag};
+use std::{
+	ops::Range,
+	path::{Path, PathBuf},
+};
 
 use crate::{
 	error::Error,
-	parsers::{date::Date, frontmatter::Frontmatter},
+	images::{ImageOptions, ResponsiveImages},
+	markdown::{
+		admonitions::admonitions,
+		excerpt::excerpt,
+		footnotes::footnotes,
+		links::{linked_posts, post_links, PostIndex, PostRef, UnresolvedLink},
+		math::math,
+		media::render_media,
+		shortcodes::{ShortcodeRegistry, SourceMap},
+	},
+	parsers::{
+		date::Date,
+		error::ParsingError,
+		frontmatter::{Frontmatter, Kind},
+	},
+	series::Series,
+	tags::{self, TagConfig},
+	templates::Layout,
 	Result,
 };
 
-pub fn read_all_posts() -> Result<Vec<Post>> {
-	let posts = std::fs::read_dir("./posts")?;
+/// Every post has to be read before any are rendered, so that links between
+/// them can be resolved.
+pub fn read_post_files() -> Result<Vec<PostFile>> {
+	let mut post_files: Vec<PostFile> = Vec::new();
+	for post in std::fs::read_dir("./posts")? {
+		post_files.push(parse_post_from_file(post?.path())?);
+	}
+	Ok(post_files)
+}
 
+pub fn render_all_posts(
+	post_files: Vec<PostFile>,
+	renderer: &MarkdownRenderer,
+	tag_config: &TagConfig,
+) -> Result<Vec<Post>> {
 	let mut parsed_posts: Vec<Post> = Vec::new();
+	let mut unresolved_links = Vec::new();
 
-	let hl = PulldownHighlighter::new("InspiredGitHub").unwrap();
-
-	for post in posts {
-		let post = parse_post_from_file(post?.path(), &hl)?;
+	for file in post_files {
+		let path = file.path.clone();
+		let (post, unresolved) = file.render(renderer)?;
+		unresolved_links
+			.extend(unresolved.into_iter().map(|link| (path.clone(), link)));
 		parsed_posts.push(post);
 	}
 
+	if !unresolved_links.is_empty() {
+		return Err(Error::UnresolvedLinks(unresolved_links));
+	}
+
 	parsed_posts.sort();
 	parsed_posts.reverse();
+	set_backlinks(&mut parsed_posts);
+
+	tag_config.resolve(&mut parsed_posts);
 
 	Ok(parsed_posts)
 }
 
+/// Fill in each post's `backlinks` from the others' `links_to`, in the same
+/// order as `posts`. A post linking to itself doesn't count.
+fn set_backlinks(posts: &mut [Post]) {
+	let backlinks = posts
+		.iter()
+		.map(|post| {
+			posts
+				.iter()
+				.filter(|other| {
+					other.href != post.href && other.links_to.contains(&post.href)
+				})
+				.map(|other| PostRef {
+					href: other.href.clone(),
+					title: other.frontmatter.title.clone(),
+				})
+				.collect::<Vec<_>>()
+		})
+		.collect::<Vec<_>>();
+	for (post, backlinks) in posts.iter_mut().zip(backlinks) {
+		post.backlinks = backlinks;
+	}
+}
+
 pub struct Post {
 	pub frontmatter: Frontmatter,
 	pub filename: String,
 	pub href: String,
 	pub date: Date,
 	pub content: Markup,
+	/// Tags from the frontmatter, filled in by
+	/// [`TagConfig::resolve`](crate::tags::TagConfig::resolve).
+	pub tags: Vec<tags::Tag>,
+	/// A plain text summary from the start of the post, see
+	/// [`excerpt`](crate::markdown::excerpt::excerpt).
+	pub excerpt: String,
+	/// Words in the post's text, not counting code.
+	pub word_count: usize,
+	/// Hrefs of the other posts this one links to.
+	pub links_to: Vec<String>,
+	/// Posts that link to this one.
+	pub backlinks: Vec<PostRef>,
+	/// The post's own stylesheets, scripts and html from its frontmatter, for
+	/// [`PageBuilder::head`](crate::page_builder::PageBuilder::head).
+	pub head: Markup,
 }
 
+/// How long an excerpt can get before it's cut off, in bytes.
+const EXCERPT_LENGTH: usize = 280;
+
+/// Average adult reading speed, in words per minute.
+const READING_SPEED: usize = 200;
+
 impl Post {
+	/// The post's description, or its excerpt if it hasn't got one.
+	pub fn summary(&self) -> &str {
+		self
+			.frontmatter
+			.description
+			.as_deref()
+			.unwrap_or(&self.excerpt)
+	}
+
+	/// How long the post takes to read, in whole minutes.
+	pub fn reading_time(&self) -> usize {
+		self.word_count.div_ceil(READING_SPEED).max(1)
+	}
+
 	pub fn as_list_item(&self) -> Markup {
 		html! {
 			.post-list-post {
@@ -45,47 +144,100 @@ impl Post {
 					.post-list-date {
 						(self.date.pretty())
 					}
+					.post-list-reading-time {
+						(self.reading_time()) " min"
+					}
 				}
 				.post-list-description {
-					(self.frontmatter.description)
+					(self.summary())
+					@if self.frontmatter.description.is_none() {
+						" "
+						a.post-list-read-more href=(self.href) { "Read more →" }
+					}
 				}
 			}
 		}
 	}
 
+	pub fn layout(&self) -> Layout {
+		match self.frontmatter.layout.as_deref() {
+			None | Some("post") => Layout::Post,
+			Some(name) => Layout::Custom(name.to_string()),
+		}
+	}
+
 	pub fn opengraph_head(&self) -> Markup {
 		html! {
 			meta property="og:title" content=(&self.frontmatter.title);
-			meta property="og:description" content=(&self.frontmatter.description);
+			meta property="og:description" content=(self.summary());
 			meta property="og:article:published_time" content=(self.date.iso_8601());
 		}
 	}
 
-	pub fn render(&self, prev: Option<&Post>, next: Option<&Post>) -> Markup {
+	pub fn render(
+		&self,
+		prev: Option<&Post>,
+		next: Option<&Post>,
+		series: Option<&Series>,
+		related: &[&Post],
+	) -> Markup {
 		html! {
 			h1.post-title { (self.frontmatter.title) }
-			.post-description {
-				(self.frontmatter.description)
+			@if let Some(description) = &self.frontmatter.description {
+				.post-description {
+					(description)
+				}
 			}
 			.post-tags {
-				@for tag in &self.frontmatter.tags {
-					a href=(format!("/tag/{}.html", tag)) { (tag) }
+				@for tag in &self.tags {
+					a href=(tag.href()) { (tag.name) }
 				}
 			}
 			time.post-date datetime=(self.date.iso_8601()) {
 				(self.date.pretty())
 			}
+			p.post-reading-time {
+				(self.word_count) " words · " (self.reading_time()) " min read"
+			}
 			hr;
-			article.markdown {
+			@if let Some(series) = series {
+				(series.render_box(self))
+			}
+			article.markdown.sidenotes[self.frontmatter.sidenotes] {
 				(self.content)
 			}
 			p.back-to-top { a href="#" { "↑ Top" } }
+			@if !self.backlinks.is_empty() {
+				hr.pn-rule;
+				.post-link-list {
+					p.post-link-list-header { "Referenced by" }
+					ul {
+						@for post in &self.backlinks {
+							li { a href=(post.href) { (post.title) } }
+						}
+					}
+				}
+			}
+			@if !related.is_empty() {
+				hr.pn-rule;
+				.post-link-list {
+					p.post-link-list-header { "Related posts" }
+					ul {
+						@for post in related {
+							li {
+								a href=(post.href) { (post.frontmatter.title) }
+								" - " (post.summary())
+							}
+						}
+					}
+				}
+			}
 			hr.pn-rule;
 			.post-prev-next {
 				@if let Some(prev) = prev {
 					.pn-item.pn-prev {
 						.pn-title { (&prev.frontmatter.title) }
-						.pn-description { (&prev.frontmatter.description) }
+						.pn-description { (prev.summary()) }
 						a.pn-link href=(&prev.href) { "← Previous" }
 					}
 				} @else {
@@ -94,7 +246,7 @@ impl Post {
 				@if let Some(next) = next {
 					.pn-item.pn-next {
 						.pn-title { (&next.frontmatter.title) }
-						.pn-description { (&next.frontmatter.description) }
+						.pn-description { (next.summary()) }
 						a.pn-link href=(&next.href) { "Next →" }
 					}
 				} @else {
@@ -107,30 +259,189 @@ impl Post {
 
 impl PartialEq for Post {
 	fn eq(&self, other: &Self) -> bool {
-		self.date.eq(&other.date)
+		self.cmp(other).is_eq()
 	}
 }
 
-impl Eq for Post {
-	fn assert_receiver_is_total_eq(&self) {}
-}
+impl Eq for Post {}
 
 impl PartialOrd for Post {
 	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-		self.date.partial_cmp(&other.date)
+		Some(self.cmp(other))
 	}
 }
 
+/// Posts are ordered by date, then file name so posts from the same day
+/// always come out in the same order.
 impl Ord for Post {
 	fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-		self.date.cmp(&other.date)
+		self
+			.date
+			.cmp(&other.date)
+			.then_with(|| self.filename.cmp(&other.filename))
+	}
+}
+
+/// What's needed to render posts' markdown, shared between all of them.
+pub struct MarkdownRenderer {
+	hl: PulldownHighlighter,
+	shortcodes: ShortcodeRegistry,
+	images: ResponsiveImages,
+	posts: PostIndex,
+}
+
+impl MarkdownRenderer {
+	pub fn new(image_options: &ImageOptions, post_files: &[PostFile]) -> Self {
+		Self {
+			hl: PulldownHighlighter::new("InspiredGitHub").unwrap(),
+			shortcodes: ShortcodeRegistry::default(),
+			images: ResponsiveImages::new(image_options.clone()),
+			posts: PostIndex::new(post_files.iter().map(|file| {
+				(
+					file.filename.as_str(),
+					PostRef {
+						href: file.href.clone(),
+						title: file.frontmatter.title.clone(),
+					},
+				)
+			})),
+		}
+	}
+
+	/// See [`ResponsiveImages::remove_unused`].
+	pub fn remove_unused_images(&self) -> Result<()> {
+		self.images.remove_unused()
+	}
+
+	/// Render the markdown in `raw` that comes after its frontmatter, which ends
+	/// at `content_start`.
+	pub fn render(
+		&self,
+		path: &Path,
+		raw: &str,
+		content_start: usize,
+		sidenotes: bool,
+	) -> Result<RenderedMarkdown> {
+		let err = |error| Error::Parsing {
+			path: PathBuf::from(path),
+			error,
+		};
+		let frontmatter_lines = raw[..content_start].matches('\n').count();
+		let (content, source_map) = self
+			.shortcodes
+			.expand(&raw[content_start..], frontmatter_lines)
+			.map_err(err)?;
+		parse_markdown(&content, self, &source_map, sidenotes).map_err(err)
+	}
+}
+
+/// A post that's been read and had its frontmatter parsed, but not rendered
+/// yet.
+pub struct PostFile {
+	pub path: PathBuf,
+	pub frontmatter: Frontmatter,
+	pub filename: String,
+	pub href: String,
+	pub date: Date,
+	raw: String,
+	/// Where the markdown starts in `raw`, after the frontmatter.
+	content_start: usize,
+}
+
+impl PostFile {
+	pub fn render(
+		self,
+		renderer: &MarkdownRenderer,
+	) -> Result<(Post, Vec<UnresolvedLink>)> {
+		let head = self.head()?;
+		let markdown = renderer.render(
+			&self.path,
+			&self.raw,
+			self.content_start,
+			self.frontmatter.sidenotes,
+		)?;
+
+		let post = Post {
+			frontmatter: self.frontmatter,
+			filename: self.filename,
+			href: self.href,
+			date: self.date,
+			content: html! { (PreEscaped(markdown.html)) },
+			tags: Vec::new(),
+			excerpt: markdown.excerpt,
+			word_count: markdown.word_count,
+			links_to: markdown.links_to,
+			backlinks: Vec::new(),
+			head,
+		};
+		Ok((post, markdown.unresolved_links))
+	}
+}
+
+impl PostFile {
+	/// The `css`, `js` and `head` files from the frontmatter.
+	fn head(&self) -> Result<Markup> {
+		let css = self
+			.frontmatter
+			.css
+			.iter()
+			.map(|file| self.embed("css", file))
+			.collect::<Result<Vec<_>>>()?;
+		let js = self
+			.frontmatter
+			.js
+			.iter()
+			.map(|file| self.embed("js", file))
+			.collect::<Result<Vec<_>>>()?;
+		let head = match &self.frontmatter.head {
+			Some(file) => {
+				let (path, _) = self.embed("head", file)?;
+				Some(std::fs::read_to_string(path)?)
+			}
+			None => None,
+		};
+
+		Ok(html! {
+			@for (_, href) in &css {
+				link rel="stylesheet" href=(href);
+			}
+			@for (_, href) in &js {
+				script src=(href) defer {}
+			}
+			@if let Some(head) = head {
+				(PreEscaped(head))
+			}
+		})
+	}
+
+	/// Find a file from the frontmatter's `key`, which has to be in the post's
+	/// folder in `static/embeds/`. Returns its path and url.
+	fn embed(&self, key: &str, file: &str) -> Result<(PathBuf, String)> {
+		let err = |reason: String| Error::InvalidPostFile {
+			path: self.path.clone(),
+			reason,
+		};
+		let relative = Path::new(file);
+		if !relative
+			.components()
+			.all(|c| matches!(c, std::path::Component::Normal(_)))
+		{
+			return Err(err(format!(
+				"{} {} should be a path inside static/embeds/{}/",
+				key, file, self.filename
+			)));
+		}
+		let path = Path::new("./static/embeds")
+			.join(&self.filename)
+			.join(relative);
+		if !path.is_file() {
+			return Err(err(format!("{} {} doesn't exist", key, path.display())));
+		}
+		Ok((path, format!("/static/embeds/{}/{}", self.filename, file)))
 	}
 }
 
-pub fn parse_post_from_file<P: AsRef<Path>>(
-	path: P,
-	hl: &PulldownHighlighter,
-) -> Result<Post> {
+pub fn parse_post_from_file<P: AsRef<Path>>(path: P) -> Result<PostFile> {
 	let path_buf = PathBuf::from(path.as_ref());
 
 	let filename_str = path
@@ -164,52 +475,134 @@ pub fn parse_post_from_file<P: AsRef<Path>>(
 
 	let raw = std::fs::read_to_string(path)?;
 
-	let (post_content, frontmatter) =
-		Frontmatter::parse(&raw).map_err(|error| Error::Parsing {
+	let (post_content, frontmatter) = Frontmatter::parse(&raw, Kind::Post)
+		.map_err(|error| Error::Parsing {
 			path: path_buf.clone(),
 			error,
 		})?;
+	let content_start = raw.len() - post_content.len();
 
-	let html_output = parse_markdown(&post_content, hl);
-
-	Ok(Post {
+	Ok(PostFile {
+		path: path_buf,
 		frontmatter,
 		filename: filename_no_ext.to_string(),
 		href,
 		date,
-		content: html! { (PreEscaped(html_output)) },
+		raw,
+		content_start,
 	})
 }
 
-fn parse_markdown(input: &str, hl: &PulldownHighlighter) -> String {
-	let parser = pulldown_cmark::Parser::new_ext(&input, markdown_options());
-	let parser = parse_markdown_custom(parser.into_iter());
-	let parser = hl.highlight(parser).unwrap();
+pub struct RenderedMarkdown {
+	pub html: String,
+	pub excerpt: String,
+	pub word_count: usize,
+	pub links_to: Vec<String>,
+	pub unresolved_links: Vec<UnresolvedLink>,
+}
+
+fn parse_markdown(
+	input: &str,
+	renderer: &MarkdownRenderer,
+	source_map: &SourceMap,
+	sidenotes: bool,
+) -> std::result::Result<RenderedMarkdown, ParsingError> {
+	let line_of = |pos: usize| source_map.line(pos);
+	let mut unresolved_links = Vec::new();
+
+	let parser = pulldown_cmark::Parser::new_ext(input, markdown_options());
+	let events = post_links(
+		parser.into_offset_iter().collect(),
+		input,
+		&renderer.posts,
+		line_of,
+		&mut unresolved_links,
+	);
+	let links_to = linked_posts(&events, &renderer.posts);
+	let events = math(events, input, line_of)?;
+	let word_count = count_words(&events);
+	let excerpt = excerpt(&events, EXCERPT_LENGTH);
+	let parser = parse_markdown_custom(
+		events.into_iter().map(|(event, _)| event),
+		&renderer.images,
+	);
+	let parser = admonitions(parser);
+	let parser = footnotes(parser, sidenotes).into_iter();
+	let parser = renderer.hl.highlight(parser).unwrap();
 	let mut html_output = String::new();
 	pulldown_cmark::html::push_html(&mut html_output, parser.into_iter());
 
-	html_output
+	Ok(RenderedMarkdown {
+		html: html_output,
+		excerpt,
+		word_count,
+		links_to,
+		unresolved_links,
+	})
+}
+
+/// Count the words in the text of some markdown, skipping code blocks. Inline
+/// code isn't text either, so it's left out too.
+fn count_words(events: &[(Event, Range<usize>)]) -> usize {
+	let mut text = String::new();
+	let mut in_code_block = false;
+	for (event, _) in events {
+		match event {
+			Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+			Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+			Event::Text(t) if !in_code_block => text.push_str(t),
+			// Text events can split a word, so only breaks and the ends of
+			// blocks separate them.
+			Event::SoftBreak | Event::HardBreak => text.push(' '),
+			Event::End(tag)
+				if !matches!(
+					tag,
+					Tag::Emphasis
+						| Tag::Strong
+						| Tag::Strikethrough
+						| Tag::Link(..)
+						| Tag::Image(..)
+				) =>
+			{
+				text.push(' ')
+			}
+			_ => {}
+		}
+	}
+	text
+		.split_whitespace()
+		.filter(|word| word.chars().any(char::is_alphanumeric))
+		.count()
 }
 
 fn parse_markdown_custom<'a, I: Iterator<Item = pulldown_cmark::Event<'a>>>(
-	iter: I,
-) -> impl Iterator<Item = pulldown_cmark::Event<'a>> {
-	use pulldown_cmark::{CowStr, Event, Tag};
-	iter.map(|event| match event {
-		Event::Start(Tag::Image(_link_type, url, title)) => {
-			let v = html! {
-				img src=(url) title=(title);
-				@if title.as_ref().trim().len() > 0 {
-					p class="markdown-image-title" { (title) }
+	mut iter: I,
+	images: &ResponsiveImages,
+) -> impl Iterator<Item = pulldown_cmark::Event<'a>> + use<'_, 'a, I> {
+	use pulldown_cmark::CowStr;
+	std::iter::from_fn(move || {
+		let event = iter.next()?;
+		Some(match event {
+			Event::Start(Tag::Image(_link_type, url, title)) => {
+				// The alt text comes through as events between the start and end of
+				// the image, gather it up instead of letting it render as plain text.
+				let mut alt = String::new();
+				for event in iter.by_ref() {
+					match event {
+						Event::End(Tag::Image(..)) => break,
+						Event::Text(text) | Event::Code(text) => alt.push_str(&text),
+						_ => {}
+					}
 				}
-			};
-			Event::Html(CowStr::from(v.0))
-		}
-		v => v,
+				let v = render_media(&url, &title, &alt, images);
+				Event::Html(CowStr::from(v.0))
+			}
+			v => v,
+		})
 	})
 }
 
-fn markdown_options() -> Options {
+pub fn markdown_options() -> Options {
 	let mut options = Options::empty();
 	options.insert(Options::ENABLE_TABLES);
 	options.insert(Options::ENABLE_STRIKETHROUGH);
@@ -217,3 +610,139 @@ fn markdown_options() -> Options {
 	options.insert(Options::ENABLE_SMART_PUNCTUATION);
 	options
 }
+
+/// A post with nothing in it, for tests.
+#[cfg(test)]
+pub fn test_post(filename: &str, date: Date) -> Post {
+	Post {
+		frontmatter: Frontmatter {
+			title: filename.to_string(),
+			description: Some(String::new()),
+			tags: Vec::new(),
+			sidenotes: false,
+			series: None,
+			series_order: None,
+			nav: false,
+			layout: None,
+			css: Vec::new(),
+			js: Vec::new(),
+			head: None,
+		},
+		filename: filename.to_string(),
+		href: format!("/posts/{}.html", filename),
+		date,
+		content: html! {},
+		tags: Vec::new(),
+		excerpt: String::new(),
+		word_count: 0,
+		links_to: Vec::new(),
+		backlinks: Vec::new(),
+		head: html! {},
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn words(input: &str) -> usize {
+		let events = pulldown_cmark::Parser::new_ext(input, markdown_options())
+			.into_offset_iter()
+			.collect();
+		count_words(&math(events, input, |_| 1).unwrap())
+	}
+
+	#[test]
+	fn test_backlinks() {
+		let post = |filename: &str, day: u16, links_to: &[&str]| {
+			let mut post = test_post(filename, Date::new(2024, 1, day));
+			post.links_to = links_to
+				.iter()
+				.map(|filename| format!("/posts/{}.html", filename))
+				.collect();
+			post
+		};
+		// Newest first, like render_all_posts sorts them.
+		let mut posts = vec![
+			post("c", 3, &["a", "c", "a"]),
+			post("b", 2, &["a", "c"]),
+			post("a", 1, &["a"]),
+		];
+		set_backlinks(&mut posts);
+		let backlinks = |i: usize| {
+			posts[i]
+				.backlinks
+				.iter()
+				.map(|post| post.href.as_str())
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(backlinks(2), ["/posts/c.html", "/posts/b.html"]);
+		assert_eq!(backlinks(0), ["/posts/b.html"]);
+		assert!(backlinks(1).is_empty());
+		assert_eq!(posts[2].backlinks[0].title, "c");
+	}
+
+	#[test]
+	fn test_media_alt_and_title() {
+		let images = ResponsiveImages::new(Default::default());
+		let html = |input: &str| {
+			let mut html = String::new();
+			pulldown_cmark::html::push_html(
+				&mut html,
+				parse_markdown_custom(
+					pulldown_cmark::Parser::new_ext(input, markdown_options()),
+					&images,
+				),
+			);
+			html
+		};
+		assert_eq!(
+			html(r#"![A *big* `gif`](https://a.com/a.gif "The caption")"#),
+			"<p><img src=\"https://a.com/a.gif\" alt=\"A big gif\" title=\"The caption\"><p class=\"markdown-image-title\">The caption</p></p>\n"
+		);
+		// Without a title there's no caption, and the alt text is the fallback.
+		assert!(html("![Some clip](/static/a.webm)")
+			.contains(r#"<a href="/static/a.webm">Some clip</a></video></p>"#));
+	}
+
+	#[test]
+	fn test_embed() {
+		let post = parse_post_from_file("./posts/2020-03-26_gouge.md").unwrap();
+		let (path, href) = post.embed("css", "Untitled 1.png").unwrap();
+		assert_eq!(
+			path,
+			Path::new("./static/embeds/2020-03-26_gouge/Untitled 1.png")
+		);
+		assert_eq!(href, "/static/embeds/2020-03-26_gouge/Untitled 1.png");
+		for file in [
+			"../x.css",
+			"/abs.css",
+			"./x.css",
+			"a/../../x.css",
+			"missing.css",
+		] {
+			assert!(
+				matches!(post.embed("css", file), Err(Error::InvalidPostFile { .. })),
+				"{}",
+				file
+			);
+		}
+	}
+
+	#[test]
+	fn test_count_words() {
+		assert_eq!(words("Some *split*ting words, and a [link](/a.html)."), 6);
+		// Code blocks and inline code aren't counted.
+		assert_eq!(
+			words("one `two`\n\n```rust\nlet three = 3;\n```\n\nfour"),
+			2
+		);
+		assert_eq!(words("one\n\n    indented code\n\ntwo"), 2);
+		// Neither is math, which is markup by the time words are counted.
+		assert_eq!(words("sum $a_i + b_i$ of\n\n$$\nx^2\n$$"), 2);
+		// Footnotes are read like the rest of the text.
+		assert_eq!(words("one[^1]\n\n[^1]: two three"), 3);
+		// Punctuation on its own isn't a word.
+		assert_eq!(words("a - b"), 2);
+	}
+}
@@ -0,0 +1,89 @@
+use crate::post::Post;
+
+/// How many related posts are listed under each post.
+pub const RELATED_POSTS: usize = 3;
+
+/// For every post, up to `count` others sharing the most tags with it, with
+/// ties going to whichever was posted closest to it, then the newer one.
+/// Posts sharing no tags aren't related at all.
+pub fn related_posts(posts: &[Post], count: usize) -> Vec<Vec<&Post>> {
+	posts
+		.iter()
+		.map(|post| {
+			let mut related = posts
+				.iter()
+				.filter(|other| other.href != post.href)
+				.map(|other| {
+					let shared = other
+						.tags
+						.iter()
+						.filter(|tag| post.tags.contains(tag))
+						.count();
+					(shared, post.date.days_between(&other.date), other)
+				})
+				.filter(|(shared, _, _)| *shared > 0)
+				.collect::<Vec<_>>();
+			related.sort_by(|a, b| {
+				b.0
+					.cmp(&a.0)
+					.then(a.1.cmp(&b.1))
+					.then(b.2.date.cmp(&a.2.date))
+					.then(a.2.href.cmp(&b.2.href))
+			});
+			related
+				.into_iter()
+				.take(count)
+				.map(|(_, _, other)| other)
+				.collect()
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{parsers::date::Date, post::test_post, tags::TagConfig};
+
+	#[test]
+	fn test_related_posts() {
+		let post = |filename: &str, day: u16, tags: &[&str]| {
+			let mut post = test_post(filename, Date::new(2024, 1, day));
+			post.frontmatter.tags = tags.iter().map(|tag| tag.to_string()).collect();
+			post
+		};
+		let mut posts = vec![
+			post("main", 10, &["rust", "bevy", "web"]),
+			post("two-tags", 1, &["rust", "bevy"]),
+			post("near", 9, &["rust"]),
+			post("newer", 11, &["web"]),
+			post("further", 12, &["bevy"]),
+			post("same-day-b", 20, &["rust"]),
+			post("same-day-a", 20, &["web"]),
+			post("unrelated", 10, &["zig"]),
+		];
+		TagConfig::default().resolve(&mut posts);
+
+		let related = related_posts(&posts, 10);
+		let names = |i: usize| {
+			related[i]
+				.iter()
+				.map(|post| post.filename.as_str())
+				.collect::<Vec<_>>()
+		};
+		// Most shared tags first, then closest in time, with the newer of two
+		// the same distance away first, then by href.
+		assert_eq!(
+			names(0),
+			[
+				"two-tags",
+				"newer",
+				"near",
+				"further",
+				"same-day-a",
+				"same-day-b"
+			]
+		);
+		assert!(names(7).is_empty());
+		assert_eq!(related_posts(&posts, 2)[0].len(), 2);
+	}
+}
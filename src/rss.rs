@@ -2,11 +2,14 @@ use crate::post::Post;
 use crate::Result;
 use maud::{html, Markup, PreEscaped};
 
-pub fn rss_feed(all_posts: &Vec<Post>) -> Result<Markup> {
+pub fn rss_feed(all_posts: &[Post]) -> Result<Markup> {
 	let most_recent_date = all_posts.iter().map(|post| &post.date).max();
 
 	Ok(html! {
-		rss xmlns:atom="http://www.w3.org/2005/Atom" version="2.0" {
+		rss
+			xmlns:atom="http://www.w3.org/2005/Atom"
+			xmlns:content="http://purl.org/rss/1.0/modules/content/"
+			version="2.0" {
 			channel {
 				title { "mcpar.land" }
 				link { "https://mcpar.land" }
@@ -29,13 +32,14 @@ impl Post {
 			item {
 				title { (&self.frontmatter.title) }
 				link { (format!("https://mcpar.land{}", self.href)) }
-				description {
+				description { (self.summary()) }
+				content:encoded {
 					(PreEscaped("<![CDATA["))
 					(&self.content)
 					(PreEscaped("]]>"))
 				}
-				@for tag in &self.frontmatter.tags {
-					category { (tag) }
+				@for tag in &self.tags {
+					category { (tag.name) }
 				}
 				pubDate { (&self.date.rfc2822()) }
 				guid isPermaLink="true" { (&self.filename) }
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+
+use maud::{html, Markup};
+
+use crate::{page_builder::PageBuilder, post::Post, util::slugify, Result};
+
+/// A series' slug, for its page's url. Unlike tags, underscores are
+/// separators, so `bevy_camera` is at `/series/bevy-camera.html`.
+pub fn slug(name: &str) -> String {
+	slugify(&name.replace('_', " "))
+}
+
+/// Posts sharing a `series` in their frontmatter.
+pub struct Series<'a> {
+	pub name: &'a str,
+	pub href: String,
+	/// In order of `series_order`, then date for posts without one.
+	pub posts: Vec<&'a Post>,
+}
+
+impl<'a> Series<'a> {
+	/// Posts are in the same series if their `series` has the same slug, so
+	/// `Bevy Camera` and `bevy camera` share a page. The series is named as
+	/// its first part spells it.
+	pub fn collect(posts: &'a [Post]) -> Vec<Series<'a>> {
+		let mut series: BTreeMap<String, Vec<&Post>> = BTreeMap::new();
+		for post in posts {
+			if let Some(name) = &post.frontmatter.series {
+				series.entry(slug(name)).or_default().push(post);
+			}
+		}
+
+		series
+			.into_iter()
+			.map(|(slug, mut posts)| {
+				posts.sort_by(|a, b| {
+					let order =
+						|post: &Post| post.frontmatter.series_order.unwrap_or(u32::MAX);
+					order(a).cmp(&order(b)).then(a.date.cmp(&b.date))
+				});
+				Series {
+					name: posts[0].frontmatter.series.as_deref().unwrap_or_default(),
+					href: format!("/series/{}.html", slug),
+					posts,
+				}
+			})
+			.collect()
+	}
+
+	/// Which part of the series a post is, counting from 0.
+	pub fn part(&self, post: &Post) -> Option<usize> {
+		self.posts.iter().position(|p| p.href == post.href)
+	}
+
+	/// The "Part N of M" box shown on each post in the series.
+	pub fn render_box(&self, post: &Post) -> Markup {
+		let part = self.part(post).unwrap_or_default();
+		let prev = part.checked_sub(1).map(|i| self.posts[i]);
+		let next = self.posts.get(part + 1);
+
+		html! {
+			aside.series-box {
+				p.series-box-header {
+					"Part " ((part + 1)) " of " (self.posts.len()) " in "
+					a href=(self.href) { (self.name) }
+				}
+				ol {
+					@for p in &self.posts {
+						li {
+							@if p.href == post.href {
+								b { (p.frontmatter.title) }
+							} @else {
+								a href=(p.href) { (p.frontmatter.title) }
+							}
+						}
+					}
+				}
+				.series-box-prev-next {
+					@if let Some(prev) = prev {
+						a href=(prev.href) { "← Part " (part) }
+					} @else {
+						div {}
+					}
+					@if let Some(next) = next {
+						a href=(next.href) { "Part " ((part + 2)) " →" }
+					}
+				}
+			}
+		}
+	}
+}
+
+pub fn gen_series_pages(
+	builder: &PageBuilder,
+	series: &[Series],
+) -> Result<()> {
+	std::fs::create_dir_all("./output/series")?;
+
+	for series in series {
+		builder
+			.clone()
+			.title(&format!("{} - john mcparland", series.name))
+			.body(html! {
+				p {
+					"A series of " (series.posts.len()) " posts, "
+					b { (series.name) }
+				}
+				ol.series-list {
+					@for post in &series.posts {
+						li { (post.as_list_item()) }
+					}
+				}
+			})
+			.write(series.href.trim_start_matches('/'))?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{parsers::date::Date, post::test_post};
+
+	fn post(filename: &str, day: u16, series: &str, order: Option<u32>) -> Post {
+		let mut post = test_post(filename, Date::new(2024, 1, day));
+		post.frontmatter.series = Some(series.to_string());
+		post.frontmatter.series_order = order;
+		post
+	}
+
+	#[test]
+	fn test_collect() {
+		let posts = vec![
+			post("c", 1, "bevy camera", None),
+			post("b", 2, "Bevy Camera", Some(2)),
+			post("a", 3, "Bevy Camera", Some(1)),
+			post("d", 4, "bevy_camera", None),
+			post("e", 5, "Other", None),
+		];
+		let series = Series::collect(&posts);
+		assert_eq!(series.len(), 2);
+		let bevy = &series[0];
+		assert_eq!(bevy.name, "Bevy Camera");
+		assert_eq!(bevy.href, "/series/bevy-camera.html");
+		// Ordered ones first, then the rest by date.
+		assert_eq!(
+			bevy
+				.posts
+				.iter()
+				.map(|p| p.filename.as_str())
+				.collect::<Vec<_>>(),
+			["a", "b", "c", "d"]
+		);
+		assert_eq!(bevy.part(&posts[0]), Some(2));
+		assert_eq!(bevy.part(&posts[4]), None);
+
+		let part = bevy.render_box(&posts[1]).0;
+		assert!(part.contains("Part 2 of 4 in"));
+		assert!(part.contains(r#"<a href="/posts/a.html">← Part 1</a>"#));
+		assert!(part.contains(r#"<a href="/posts/c.html">Part 3 →</a>"#));
+		let first = bevy.render_box(&posts[2]).0;
+		assert!(first.contains("Part 1 of 4 in"));
+		assert!(!first.contains("← Part"));
+	}
+}
@@ -0,0 +1,184 @@
+use std::{fs, path::Path};
+
+use crate::{
+	assets::{is_hash, AssetManifest},
+	util::fnv1a,
+	Result,
+};
+
+/// The site's stylesheet, read once per build. It's either inlined into a
+/// `<style>` on every page, or minified into its own file with a hash of its
+/// contents in the name, so browsers can cache it for as long as they like.
+#[derive(Default)]
+pub struct Stylesheet {
+	pub css: String,
+	/// Where the external file is, if there is one.
+	pub href: Option<String>,
+}
+
+impl Stylesheet {
+	/// The urls in it point at the fingerprinted copies from `assets`, before
+	/// it's hashed itself.
+	pub fn load<P: AsRef<Path>>(
+		path: P,
+		external: bool,
+		assets: &AssetManifest,
+	) -> Result<Self> {
+		let css = assets.rewrite_css(&fs::read_to_string(path)?);
+		if !external {
+			return Ok(Self { css, href: None });
+		}
+		let css = minify(&css);
+		let href = format!("/static/styles.{:016x}.css", fnv1a(css.as_bytes()));
+		Ok(Self {
+			css,
+			href: Some(href),
+		})
+	}
+
+	/// Write the external file into `output`, removing any from earlier
+	/// builds, even when this one inlines the stylesheet. Only names like
+	/// `styles.<hash>.css` are ours, so a `static/styles.print.css` is kept.
+	pub fn write<P: AsRef<Path>>(&self, output: P) -> Result<()> {
+		let dir = output.as_ref().join("static");
+		fs::create_dir_all(&dir)?;
+		let current = self
+			.href
+			.as_deref()
+			.and_then(|href| href.rsplit('/').next());
+		for entry in fs::read_dir(&dir)? {
+			let file = entry?.path();
+			let name = file.file_name().unwrap_or_default().to_string_lossy();
+			let generated = name
+				.strip_prefix("styles.")
+				.and_then(|name| name.strip_suffix(".css"))
+				.is_some_and(is_hash);
+			if generated && file.is_file() && Some(name.as_ref()) != current {
+				fs::remove_file(&file)?;
+			}
+		}
+		let Some(href) = &self.href else {
+			return Ok(());
+		};
+		println!("🎨 {}", href.trim_start_matches('/'));
+		fs::write(
+			output.as_ref().join(href.trim_start_matches('/')),
+			&self.css,
+		)?;
+		Ok(())
+	}
+}
+
+/// Strip comments and any whitespace that doesn't change what the css means.
+/// Spaces are only taken out around `{`, `}`, `;`, `,` and `>`, since the
+/// ones around `:`, `+` and the like can matter in selectors and `calc()`.
+/// Strings are left alone.
+fn minify(css: &str) -> String {
+	let mut out = String::with_capacity(css.len());
+	let mut chars = css.chars().peekable();
+	let mut space = false;
+
+	let trims = |c: char| matches!(c, '{' | '}' | ';' | ',' | '>');
+
+	while let Some(c) = chars.next() {
+		match c {
+			'/' if chars.peek() == Some(&'*') => {
+				chars.next();
+				let mut prev = ' ';
+				for c in chars.by_ref() {
+					if prev == '*' && c == '/' {
+						break;
+					}
+					prev = c;
+				}
+			}
+			c if c.is_whitespace() => space = true,
+			'"' | '\'' => {
+				if space && !out.ends_with(trims) && !out.is_empty() {
+					out.push(' ');
+				}
+				space = false;
+				out.push(c);
+				let quote = c;
+				while let Some(c) = chars.next() {
+					out.push(c);
+					if c == '\\' {
+						out.extend(chars.next());
+					} else if c == quote {
+						break;
+					}
+				}
+			}
+			c => {
+				if c == '}' && out.ends_with(';') {
+					out.pop();
+				}
+				if space && !trims(c) && !out.ends_with(trims) && !out.is_empty() {
+					out.push(' ');
+				}
+				space = false;
+				out.push(c);
+			}
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_write() {
+		let output =
+			std::env::temp_dir().join(format!("styles-{}", std::process::id()));
+		let file = |name: &str| output.join("static").join(name);
+		fs::create_dir_all(file("")).unwrap();
+		// Written by an earlier build, and real files from `static/`.
+		for name in [
+			"styles.0123456789abcdef.css",
+			"styles.print.css",
+			"styles.0123456789abcdef.min.css",
+			"styles.css",
+		] {
+			fs::write(file(name), "").unwrap();
+		}
+
+		let stylesheet =
+			Stylesheet::load("./styles.css", true, &AssetManifest::default())
+				.unwrap();
+		stylesheet.write(&output).unwrap();
+		let href = stylesheet.href.as_deref().unwrap();
+		let written = file(href.trim_start_matches("/static/"));
+		assert_eq!(fs::read_to_string(&written).unwrap(), stylesheet.css);
+		assert!(!file("styles.0123456789abcdef.css").exists());
+		assert!(file("styles.print.css").exists());
+		assert!(file("styles.0123456789abcdef.min.css").exists());
+		assert!(file("styles.css").exists());
+
+		// Inlining it next time removes the file.
+		Stylesheet::default().write(&output).unwrap();
+		assert!(!written.exists());
+		assert!(file("styles.print.css").exists());
+		fs::remove_dir_all(output).unwrap();
+	}
+
+	#[test]
+	fn test_minify() {
+		let css = r#"/* header */
+.a > .b,
+.c :hover {
+  width: calc(600px + (100px * 2));
+  content: "  { spaced; }  ";
+}
+
+.d {
+  img { height: auto; }
+}
+"#;
+		assert_eq!(
+			minify(css),
+			r#".a>.b,.c :hover{width: calc(600px + (100px * 2));content: "  { spaced; }  "}.d{img{height: auto}}"#
+		);
+	}
+}
@@ -1,71 +1,550 @@
-use std::collections::HashMap;
+use std::{
+	collections::{BTreeMap, HashMap},
+	path::{Path, PathBuf},
+};
 
 use maud::{html, Markup};
 
-use crate::{page_builder::PageBuilder, post::Post, Result};
+use crate::{
+	blog::{list_links, post_list_with_dates},
+	error::Error,
+	page_builder::PageBuilder,
+	pagination::{paginate, Page},
+	parsers::{error::ParsingError, toml},
+	post::Post,
+	templates::{tag_vars, Layout},
+	util::{slugify, slugify_path},
+	Result,
+};
 
-pub fn gen_tag_pages(builder: &PageBuilder, posts: &Vec<Post>) -> Result<()> {
-	std::fs::create_dir_all("./output/tag")?;
+/// A tag as it's shown on the site. Tags are told apart by their slug, so
+/// `Rust`, `rust` and `rust ` are all the same tag.
+///
+/// Tags can be nested with a `/`: `rust/bevy` is a child of `rust`, and is
+/// named just `Bevy`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tag {
+	pub slug: String,
+	pub name: String,
+	pub parent: Option<Box<Tag>>,
+}
 
-	let mut tags: HashMap<&str, Vec<&Post>> = HashMap::new();
+impl Tag {
+	pub fn href(&self) -> String {
+		format!("/tag/{}.html", self.slug)
+	}
 
-	for post in posts.iter() {
-		for tag in post.frontmatter.tags.iter() {
-			if !tags.contains_key(&tag.as_str()) {
-				tags.insert(tag.as_str(), Vec::new());
+	/// The tag's parent, its parent's parent and so on, outermost first.
+	pub fn ancestors(&self) -> Vec<&Tag> {
+		let mut ancestors = Vec::new();
+		let mut tag = self;
+		while let Some(parent) = &tag.parent {
+			ancestors.push(parent.as_ref());
+			tag = parent;
+		}
+		ancestors.reverse();
+		ancestors
+	}
+}
+
+/// The slug and spelling of each level of a tag, so `Rust/Bevy` is
+/// `[("rust", "Rust"), ("rust/bevy", "Bevy")]`.
+fn levels(tag: &str) -> Vec<(String, &str)> {
+	let mut slug = String::new();
+	let mut levels = Vec::new();
+	for part in tag.split('/').map(str::trim) {
+		if slugify(part).is_empty() {
+			continue;
+		}
+		if !slug.is_empty() {
+			slug.push('/');
+		}
+		slug.push_str(&slugify(part));
+		levels.push((slug.clone(), part));
+	}
+	levels
+}
+
+/// Display names and descriptions for tags, read from an optional
+/// `tags.toml` with a table for each tag's slug:
+///
+/// ```toml
+/// [game-dev]
+/// name = "Game Dev"
+/// description = "Making games, mostly in Bevy."
+///
+/// [rust.bevy]
+/// description = "The Bevy game engine."
+/// ```
+///
+/// Child tags are tables inside their parent's table, or have the whole slug
+/// quoted, like `["rust/bevy"]`.
+#[derive(Default)]
+pub struct TagConfig {
+	tags: HashMap<String, TagInfo>,
+}
+
+#[derive(Default)]
+struct TagInfo {
+	name: Option<String>,
+	description: Option<String>,
+}
+
+impl TagConfig {
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+		let path = path.as_ref();
+		if !path.is_file() {
+			return Ok(Self::default());
+		}
+		Self::parse(&std::fs::read_to_string(path)?).map_err(|error| {
+			Error::Parsing {
+				path: PathBuf::from(path),
+				error,
 			}
-			let tags = tags.get_mut(tag.as_str()).unwrap();
-			tags.push(post);
+		})
+	}
+
+	fn parse(input: &str) -> std::result::Result<Self, ParsingError> {
+		let mut config = Self::default();
+		for (slug, value) in toml::parse(input)? {
+			config
+				.load_tag(&slugify_path(&slug), &value)
+				.map_err(ParsingError::InvalidTagConfig)?;
 		}
+		Ok(config)
 	}
 
-	builder
-		.clone()
-		.body(html! {
-			p {
-				a href="/blog.html" { "Posts by date" }
-				" - ";
-				"Posts by tag";
+	fn load_tag(
+		&mut self,
+		slug: &str,
+		value: &toml::Value,
+	) -> std::result::Result<(), String> {
+		let table = value
+			.as_table()
+			.ok_or_else(|| format!("{} should be a table", slug))?;
+		if self.tags.contains_key(slug) {
+			// Like `[Rust]` and `[rust]`.
+			return Err(format!("{} is in there twice", slug));
+		}
+		let mut info = TagInfo::default();
+		for (key, value) in table {
+			if let toml::Value::Table(_) = value {
+				self.load_tag(&format!("{}/{}", slug, slugify_path(key)), value)?;
+				continue;
 			}
-			ul {
-				@for (name, posts) in &tags {
-					li {
-						a href=(format!("/tag/{}.html", name)) { (name) }
-						" - ";
-						(posts.len());
-						" post";
-						@if posts.len() > 1 { "s" }
+			let value = value
+				.as_str()
+				.ok_or_else(|| format!("{}.{} should be a string", slug, key))?
+				.to_string();
+			match key.as_str() {
+				"name" => info.name = Some(value),
+				"description" => info.description = Some(value),
+				key => return Err(format!("unknown key {}.{}", slug, key)),
+			}
+		}
+		self.tags.insert(slug.to_string(), info);
+		Ok(())
+	}
+
+	pub fn description(&self, tag: &Tag) -> Option<&str> {
+		self.tags.get(&tag.slug)?.description.as_deref()
+	}
+
+	/// Work out the tags of every post from what's in their frontmatter. A tag
+	/// is named by `tags.toml` if it's in there, otherwise it keeps the
+	/// spelling from the newest post that has it.
+	pub fn resolve(&self, posts: &mut [Post]) {
+		let mut spellings: HashMap<String, (&Post, &str)> = HashMap::new();
+		for post in posts.iter() {
+			for (slug, part) in post.frontmatter.tags.iter().flat_map(|t| levels(t)) {
+				let spelling = spellings.entry(slug).or_insert((post, part));
+				if post > spelling.0 {
+					*spelling = (post, part);
+				}
+			}
+		}
+		let names = spellings
+			.into_iter()
+			.map(|(slug, (_, part))| (slug, part.to_string()))
+			.collect::<HashMap<_, _>>();
+		for slug in self.tags.keys() {
+			if !names.contains_key(slug) {
+				println!("⚠️  tags.toml has {}, which no post uses", slug);
+			}
+		}
+
+		for post in posts.iter_mut() {
+			post.tags = post
+				.frontmatter
+				.tags
+				.iter()
+				.map(|tag| {
+					let mut parent = None;
+					for (slug, _) in levels(tag) {
+						let name = self
+							.tags
+							.get(&slug)
+							.and_then(|info| info.name.clone())
+							.unwrap_or_else(|| names[&slug].clone());
+						parent = Some(Box::new(Tag { slug, name, parent }));
 					}
+					*parent.expect("tags aren't empty")
+				})
+				.collect();
+		}
+	}
+}
+
+/// The order tags are listed in on `tags.html`.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum TagOrder {
+	#[default]
+	Alphabetical,
+	/// Most posts first, then alphabetical.
+	Count,
+}
+
+impl TagOrder {
+	pub fn parse(order: &str) -> Option<Self> {
+		match order {
+			"alphabetical" => Some(Self::Alphabetical),
+			"count" => Some(Self::Count),
+			_ => None,
+		}
+	}
+}
+
+pub fn gen_tag_pages(
+	builder: &PageBuilder,
+	posts: &[Post],
+	config: &TagConfig,
+	order: TagOrder,
+	page_size: usize,
+) -> Result<()> {
+	std::fs::create_dir_all("./output/tag")?;
+
+	let tags = group_tags(posts, order);
+
+	builder.clone().body(tag_index(&tags)).write("tags.html")?;
+
+	for (tag, posts) in &tags {
+		let base = format!("/tag/{}", tag.slug);
+		let description = config.description(tag);
+		let children = children(&tags, Some(tag));
+		for page in paginate(posts, page_size, &base) {
+			builder
+				.clone()
+				.head(page.head())
+				.layout(Layout::Tag, tag_vars(tag, description, &children, &page))
+				.body(gen_tag_page(tag, description, &children, &page))
+				.write(page.path())?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Every tag with its posts, newest first. A parent tag has all of its
+/// children's posts too.
+fn group_tags(posts: &[Post], order: TagOrder) -> Vec<(&Tag, Vec<&Post>)> {
+	let mut tags: BTreeMap<&str, (&Tag, Vec<&Post>)> = BTreeMap::new();
+
+	for post in posts.iter() {
+		for tag in post.tags.iter() {
+			for tag in tag.ancestors().into_iter().chain([tag]) {
+				let (_, posts) = tags.entry(&tag.slug).or_insert((tag, Vec::new()));
+				// A post tagged both `rust` and `rust/bevy` is only in `rust` once.
+				if !posts.last().is_some_and(|last| std::ptr::eq(*last, post)) {
+					posts.push(post);
 				}
 			}
+		}
+	}
+
+	let mut tags = tags.into_values().collect::<Vec<_>>();
+	for (_, posts) in tags.iter_mut() {
+		posts.sort_by(|a, b| b.cmp(a));
+	}
+	if order == TagOrder::Count {
+		// Stable, so tags with the same count stay alphabetical.
+		tags.sort_by_key(|(_, posts)| std::cmp::Reverse(posts.len()));
+	}
+	tags
+}
+
+/// The tags directly under `parent`, or the top level tags for `None`, in the
+/// same order as `tags`.
+fn children<'a>(
+	tags: &[(&'a Tag, Vec<&Post>)],
+	parent: Option<&Tag>,
+) -> Vec<&'a Tag> {
+	tags
+		.iter()
+		.map(|(tag, _)| *tag)
+		.filter(|tag| {
+			tag.parent.as_ref().map(|p| &p.slug) == parent.map(|p| &p.slug)
 		})
-		.write("tags.html")?;
+		.collect()
+}
 
-	for (name, posts) in tags {
-		builder
-			.clone()
-			.body(gen_tag_page(name, &posts))
-			.write(format!("tag/{}.html", name))?;
+fn tag_index(tags: &[(&Tag, Vec<&Post>)]) -> Markup {
+	let counts = tags.iter().map(|(_, posts)| posts.len());
+	let min = counts.clone().min().unwrap_or(0);
+	let max = counts.max().unwrap_or(0);
+	// From 1 to 5, for the tag cloud's font sizes.
+	let weight = |count: usize| {
+		if max == min {
+			3
+		} else {
+			1 + (count - min) * 4 / (max - min)
+		}
+	};
+
+	html! {
+		(list_links("/tags.html"))
+		p.tag-cloud {
+			@for (tag, posts) in tags {
+				a class=(format!("tag-weight-{}", weight(posts.len()))) href=(tag.href()) {
+					(tag.name)
+				}
+				" "
+			}
+		}
+		(tag_tree(tags, None))
 	}
+}
 
-	Ok(())
+/// A nested list of the tags under `parent`, and the tags under those.
+fn tag_tree(tags: &[(&Tag, Vec<&Post>)], parent: Option<&Tag>) -> Markup {
+	let count = |tag: &Tag| {
+		let (_, posts) = tags.iter().find(|(t, _)| t.slug == tag.slug).unwrap();
+		posts.len()
+	};
+
+	html! {
+		ul.tag-tree {
+			@for tag in children(tags, parent) {
+				li {
+					a href=(tag.href()) { (tag.name) }
+					" - ";
+					(count(tag));
+					" post";
+					@if count(tag) > 1 { "s" }
+					@if !children(tags, Some(tag)).is_empty() {
+						(tag_tree(tags, Some(tag)))
+					}
+				}
+			}
+		}
+	}
 }
 
-fn gen_tag_page(name: &str, posts: &Vec<&Post>) -> Markup {
+fn gen_tag_page(
+	tag: &Tag,
+	description: Option<&str>,
+	children: &[&Tag],
+	page: &Page<&Post>,
+) -> Markup {
 	html! {
-		p {
-			a href="/blog.html" { "Posts by date" }
-			" - ";
-			a href="/tags.html" { "Posts by tag" }
+		(list_links(""))
+		@if tag.parent.is_some() {
+			nav.tag-breadcrumbs aria-label="Parent tags" {
+				@for ancestor in tag.ancestors() {
+					a href=(ancestor.href()) { (ancestor.name) }
+					" › ";
+				}
+				(tag.name)
+			}
 		}
 		p {
 			"All posts tagged ";
-			b { (name) }
+			b { (tag.name) }
+		}
+		@if let Some(description) = description {
+			p.tag-description { (description) }
+		}
+		@if !children.is_empty() {
+			p.tag-children {
+				"Including ";
+				@for (i, child) in children.iter().enumerate() {
+					@if i > 0 { ", " }
+					a href=(child.href()) { (child.name) }
+				}
+			}
 		}
-		.post-list {
-			@for post in posts {
-				(post.as_list_item())
+		(post_list_with_dates(page.items.iter().copied()))
+		(page.nav())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{parsers::date::Date, post::test_post};
+
+	fn post(filename: &str, date: Date, tags: &[&str]) -> Post {
+		let mut post = test_post(filename, date);
+		post.frontmatter.tags = tags.iter().map(|tag| tag.to_string()).collect();
+		post
+	}
+
+	fn posts() -> Vec<Post> {
+		let mut posts = vec![
+			post("b", Date::new(2024, 1, 1), &["rust", "Web"]),
+			post("a", Date::new(2024, 1, 1), &["zig", "rust"]),
+			post("c", Date::new(2023, 6, 1), &["Rust", "web", "bevy"]),
+			post("d", Date::new(2024, 3, 1), &["web"]),
+		];
+		TagConfig::default().resolve(&mut posts);
+		posts
+	}
+
+	fn render(posts: &[Post], order: TagOrder) -> Vec<String> {
+		let tags = group_tags(posts, order);
+		let mut pages = vec![tag_index(&tags).0];
+		for (tag, posts) in &tags {
+			let children = children(&tags, Some(tag));
+			for page in paginate(posts, 2, "/tag") {
+				pages.push(gen_tag_page(tag, None, &children, &page).0);
 			}
 		}
+		pages
+	}
+
+	#[test]
+	fn test_config() {
+		let config = TagConfig::parse(
+			r#"
+[game-dev]
+name = "Game Dev"
+description = "Making games."
+
+[rust]
+description = "Rust."
+
+[rust.bevy]
+name = "Bevy"
+
+[rust.bevy.ui]
+description = "Menus."
+
+["web/css"]
+name = "CSS"
+"#,
+		)
+		.unwrap();
+		let info = |slug: &str| {
+			let info = &config.tags[slug];
+			(info.name.as_deref(), info.description.as_deref())
+		};
+		assert_eq!(info("game-dev"), (Some("Game Dev"), Some("Making games.")));
+		assert_eq!(info("rust"), (None, Some("Rust.")));
+		// Child tags are nested tables, or quoted slugs.
+		assert_eq!(info("rust/bevy"), (Some("Bevy"), None));
+		assert_eq!(info("rust/bevy/ui"), (None, Some("Menus.")));
+		assert_eq!(info("web/css"), (Some("CSS"), None));
+		assert_eq!(config.tags.len(), 5);
+
+		for bad in [
+			"[a]\ncolor = \"red\"",
+			"[a]\nname = true",
+			"a = \"b\"",
+			"[a]\nname = 1",
+			"[Rust]\n[rust.bevy]",
+		] {
+			assert!(TagConfig::parse(bad).is_err(), "{}", bad);
+		}
+	}
+
+	#[test]
+	fn test_order() {
+		let posts = posts();
+		let slugs = |order| {
+			group_tags(&posts, order)
+				.iter()
+				.map(|(tag, posts)| {
+					let filenames = posts.iter().map(|p| p.filename.as_str());
+					(tag.slug.as_str(), filenames.collect::<Vec<_>>())
+				})
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(
+			slugs(TagOrder::Alphabetical),
+			[
+				("bevy", vec!["c"]),
+				("rust", vec!["b", "a", "c"]),
+				("web", vec!["d", "b", "c"]),
+				("zig", vec!["a"]),
+			]
+		);
+		let by_count = slugs(TagOrder::Count);
+		assert_eq!(
+			by_count.iter().map(|(slug, _)| *slug).collect::<Vec<_>>(),
+			["rust", "web", "bevy", "zig"]
+		);
+	}
+
+	#[test]
+	fn test_hierarchy() {
+		let mut posts = vec![
+			post("a", Date::new(2024, 1, 1), &["Rust/Bevy"]),
+			post("b", Date::new(2024, 2, 1), &["Rust", "Rust/Bevy/ECS"]),
+			post("c", Date::new(2024, 3, 1), &["Rust/ wasm "]),
+		];
+		TagConfig::default().resolve(&mut posts);
+
+		let ecs = &posts[1].tags[1];
+		assert_eq!(ecs.slug, "rust/bevy/ecs");
+		assert_eq!(ecs.name, "ECS");
+		assert_eq!(ecs.href(), "/tag/rust/bevy/ecs.html");
+		let ancestors = ecs.ancestors();
+		assert_eq!(
+			ancestors
+				.iter()
+				.map(|t| t.name.as_str())
+				.collect::<Vec<_>>(),
+			["Rust", "Bevy"]
+		);
+
+		let tags = group_tags(&posts, TagOrder::Alphabetical);
+		let filenames = |slug: &str| {
+			let (_, posts) = tags.iter().find(|(tag, _)| tag.slug == slug).unwrap();
+			posts
+				.iter()
+				.map(|p| p.filename.as_str())
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(filenames("rust"), ["c", "b", "a"]);
+		assert_eq!(filenames("rust/bevy"), ["b", "a"]);
+		assert_eq!(filenames("rust/wasm"), ["c"]);
+
+		let slugs = |parent| {
+			children(&tags, parent)
+				.iter()
+				.map(|tag| tag.slug.as_str())
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(slugs(None), ["rust"]);
+		assert_eq!(slugs(Some(ancestors[0])), ["rust/bevy", "rust/wasm"]);
+		assert_eq!(slugs(Some(ecs)), Vec::<&str>::new());
+	}
+
+	#[test]
+	fn test_identical_output() {
+		let first = render(&posts(), TagOrder::Alphabetical);
+		for _ in 0..10 {
+			assert_eq!(render(&posts(), TagOrder::Alphabetical), first);
+		}
+
+		// Posts aren't guaranteed to be in any order before they're sorted.
+		let mut shuffled = posts();
+		shuffled.reverse();
+		shuffled.swap(0, 2);
+		TagConfig::default().resolve(&mut shuffled);
+		assert_eq!(render(&shuffled, TagOrder::Alphabetical), first);
+		assert_eq!(
+			render(&shuffled, TagOrder::Count),
+			render(&posts(), TagOrder::Count)
+		);
 	}
 }
@@ -0,0 +1,363 @@
+use std::{
+	borrow::Borrow,
+	collections::{BTreeMap, HashMap},
+	path::{Path, PathBuf},
+};
+
+use minijinja::{Environment, UndefinedBehavior, Value};
+
+use crate::{
+	error::Error, page_builder::NavLink, pagination::Page,
+	parsers::error::ParsingError, post::Post, tags::Tag, Result,
+};
+
+/// The layouts pages are built with. Each can be replaced by a template of the
+/// same name in `templates/`, like `templates/post.html`, and otherwise uses
+/// the built-in `maud` one.
+///
+/// `base` is the whole page around the body, and gets `title`, `description`,
+/// `head`, `body`, `nav` and `footer`, the `stylesheet` as a `<link>` or
+/// `<style>`, and all of the `css`. The others make the body of a
+/// post, a page of the blog list or a tag page, and get the variables from
+/// [`post_vars`], [`list_vars`] and [`tag_vars`], plus `body` as the built-in
+/// layout would have made it.
+///
+/// A post can also pick any other template in `templates/` with `layout` in
+/// its frontmatter, which gets the same variables as `post`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Layout {
+	#[default]
+	Base,
+	Post,
+	List,
+	Tag,
+	Custom(String),
+}
+
+impl Layout {
+	pub fn name(&self) -> &str {
+		match self {
+			Layout::Base => "base",
+			Layout::Post => "post",
+			Layout::List => "list",
+			Layout::Tag => "tag",
+			Layout::Custom(name) => name,
+		}
+	}
+}
+
+/// A template's variables, by name.
+pub type Vars = BTreeMap<String, Value>;
+
+/// The templates found in `templates/`, by name. They're Jinja templates,
+/// rendered with [`minijinja`]. Html in variables is escaped unless it's
+/// printed with `| safe`, and printing a variable that isn't there is an
+/// error, though `{% if x %}` is fine.
+pub struct Templates {
+	env: Environment<'static>,
+	paths: HashMap<String, PathBuf>,
+}
+
+impl Default for Templates {
+	fn default() -> Self {
+		let mut env = Environment::new();
+		env.set_undefined_behavior(UndefinedBehavior::SemiStrict);
+		Self {
+			env,
+			paths: HashMap::new(),
+		}
+	}
+}
+
+impl Templates {
+	/// Read every layout's template from `dir`, which doesn't have to exist.
+	pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self> {
+		let dir = dir.as_ref();
+		let mut templates = Self::default();
+		if !dir.is_dir() {
+			return Ok(templates);
+		}
+
+		for entry in std::fs::read_dir(dir)? {
+			let path = entry?.path();
+			let name = path
+				.file_name()
+				.and_then(|name| name.to_str()?.strip_suffix(".html"));
+			let Some(name) = name.map(str::to_string) else {
+				println!("⚠️  {} isn't a template", path.display());
+				continue;
+			};
+			let source = std::fs::read_to_string(&path)?;
+			templates
+				.env
+				.add_template_owned(format!("{}.html", name), source)
+				.map_err(|error| Error::Parsing {
+					path: path.clone(),
+					error: template_error(error),
+				})?;
+			templates.paths.insert(name, path);
+		}
+		Ok(templates)
+	}
+
+	/// Make sure every post's `layout` has a template. The built-in layouts
+	/// other than `post` are for other kinds of page, so can't be used.
+	pub fn check_layouts(&self, posts: &[Post]) -> Result<()> {
+		for post in posts {
+			let Some(layout) = &post.frontmatter.layout else {
+				continue;
+			};
+			let reason = if ["base", "list", "tag"].contains(&layout.as_str()) {
+				format!("the {} layout isn't for posts", layout)
+			} else if layout != "post" && !self.paths.contains_key(layout) {
+				format!("there's no templates/{}.html for its layout", layout)
+			} else {
+				continue;
+			};
+			return Err(Error::InvalidPostFile {
+				path: PathBuf::from(format!("./posts/{}.md", post.filename)),
+				reason,
+			});
+		}
+		Ok(())
+	}
+
+	/// Render `layout`'s template with `vars`, or `None` if there isn't one.
+	pub fn render(&self, layout: &Layout, vars: &Vars) -> Option<Result<String>> {
+		let path = self.paths.get(layout.name())?;
+		let rendered = self
+			.env
+			.get_template(&format!("{}.html", layout.name()))
+			.and_then(|template| template.render(vars));
+		Some(rendered.map_err(|error| Error::Parsing {
+			path: path.clone(),
+			error: template_error(error),
+		}))
+	}
+}
+
+fn template_error(error: minijinja::Error) -> ParsingError {
+	ParsingError::InvalidTemplate {
+		line: error.line().unwrap_or(1),
+		reason: match error.detail() {
+			Some(detail) => format!("{}: {}", error.kind(), detail),
+			None => error.kind().to_string(),
+		},
+	}
+}
+
+fn table<const N: usize>(entries: [(&str, Value); N]) -> Value {
+	entries.into_iter().collect()
+}
+
+fn string(s: impl Into<String>) -> Value {
+	Value::from(s.into())
+}
+
+pub fn nav_vars(links: &[NavLink], active: Option<&str>) -> Value {
+	links
+		.iter()
+		.map(|link| {
+			table([
+				("label", string(&link.label)),
+				("href", string(&link.href)),
+				("external", Value::from(link.external)),
+				("icon", string(link.icon.clone().unwrap_or_default())),
+				("current", Value::from(active == Some(link.href.as_str()))),
+			])
+		})
+		.collect()
+}
+
+fn tag_fields(tag: &Tag) -> Vec<(&'static str, Value)> {
+	vec![
+		("name", string(&tag.name)),
+		("slug", string(&tag.slug)),
+		("href", string(tag.href())),
+	]
+}
+
+fn tag_value(tag: &Tag) -> Value {
+	tag_fields(tag).into_iter().collect()
+}
+
+fn post_fields(post: &Post) -> Vec<(&'static str, Value)> {
+	vec![
+		("title", string(&post.frontmatter.title)),
+		(
+			"description",
+			string(post.frontmatter.description.clone().unwrap_or_default()),
+		),
+		("summary", string(post.summary())),
+		("href", string(&post.href)),
+		("date", string(post.date.pretty())),
+		("datetime", string(post.date.iso_8601())),
+		("tags", post.tags.iter().map(tag_value).collect()),
+		("word_count", Value::from(post.word_count)),
+		("reading_time", Value::from(post.reading_time())),
+	]
+}
+
+fn page_value<T>(page: &Page<T>) -> Value {
+	table([
+		("number", Value::from(page.number)),
+		("count", Value::from(page.count)),
+		("prev", string(page.prev().unwrap_or_default())),
+		("next", string(page.next().unwrap_or_default())),
+	])
+}
+
+/// `post`, with the post's `title`, `description`, `summary`, `href`, `date`,
+/// `datetime`, `tags`, `word_count` and `reading_time`, and its html as
+/// `content`.
+pub fn post_vars(post: &Post) -> Vars {
+	let mut fields = post_fields(post);
+	fields.push(("content", string(post.content.0.clone())));
+	Vars::from([("post".to_string(), fields.into_iter().collect())])
+}
+
+/// `posts` on the page, and `page` with its `number`, the `count` of pages,
+/// and the `prev` and `next` pages' hrefs.
+pub fn list_vars<T: Borrow<Post>>(page: &Page<T>) -> Vars {
+	Vars::from([
+		(
+			"posts".to_string(),
+			page
+				.items
+				.iter()
+				.map(|p| post_fields(p.borrow()).into_iter().collect::<Value>())
+				.collect(),
+		),
+		("page".to_string(), page_value(page)),
+	])
+}
+
+/// [`list_vars`], and the `tag` with its `name`, `slug`, `href` and
+/// `description`, its `parents` and its `children`.
+pub fn tag_vars(
+	tag: &Tag,
+	description: Option<&str>,
+	children: &[&Tag],
+	page: &Page<&Post>,
+) -> Vars {
+	let mut fields = tag_fields(tag);
+	fields.push(("description", string(description.unwrap_or_default())));
+	let mut vars = list_vars(page);
+	vars.insert("tag".to_string(), fields.into_iter().collect());
+	vars.insert(
+		"parents".to_string(),
+		tag.ancestors().into_iter().map(tag_value).collect(),
+	);
+	vars.insert(
+		"children".to_string(),
+		children.iter().map(|tag| tag_value(tag)).collect(),
+	);
+	vars
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn templates(source: &str) -> Templates {
+		let mut templates = Templates::default();
+		templates
+			.env
+			.add_template_owned("post.html", source.to_string())
+			.unwrap();
+		templates
+			.paths
+			.insert("post".to_string(), PathBuf::from("templates/post.html"));
+		templates
+	}
+
+	fn render(source: &str, vars: &Vars) -> Result<String> {
+		templates(source).render(&Layout::Post, vars).unwrap()
+	}
+
+	#[test]
+	fn test_render() {
+		let vars = Vars::from([
+			("title".to_string(), string("<Hi>")),
+			("body".to_string(), string("<p>body</p>")),
+			(
+				"tags".to_string(),
+				Value::from(vec![
+					table([("name", string("rust"))]),
+					table([("name", string("web"))]),
+				]),
+			),
+			("empty".to_string(), Value::from(Vec::<Value>::new())),
+		]);
+		assert_eq!(
+			render(
+				"{{ title }}{{ body | safe }}{% for tag in tags %}[{{ tag.name }}]{% endfor %}",
+				&vars
+			)
+			.unwrap(),
+			"&lt;Hi&gt;<p>body</p>[rust][web]"
+		);
+		assert_eq!(
+			render(
+				"{% if empty %}a{% else %}b{% endif %}{% if not missing %}c{% endif %}",
+				&vars
+			)
+			.unwrap(),
+			"bc"
+		);
+		assert!(templates("").render(&Layout::Tag, &vars).is_none());
+
+		match render("\n{{ missing }}", &vars) {
+			Err(Error::Parsing {
+				error: ParsingError::InvalidTemplate { line, .. },
+				..
+			}) => assert_eq!(line, 2),
+			other => panic!("{:?}", other.map_err(|_| ())),
+		}
+	}
+
+	#[test]
+	fn test_check_layouts() {
+		use crate::{parsers::date::Date, post::test_post};
+
+		let mut templates = templates("");
+		templates
+			.paths
+			.insert("wide".to_string(), PathBuf::from("templates/wide.html"));
+		let check = |layout: &str| {
+			let mut post = test_post("a", Date::new(2024, 1, 1));
+			post.frontmatter.layout = Some(layout.to_string());
+			templates.check_layouts(&[post])
+		};
+		assert!(check("post").is_ok());
+		assert!(check("wide").is_ok());
+		for layout in ["base", "list", "tag", "missing"] {
+			assert!(
+				matches!(check(layout), Err(Error::InvalidPostFile { .. })),
+				"{}",
+				layout
+			);
+		}
+	}
+
+	#[test]
+	fn test_syntax_errors() {
+		let line = |source: &str| {
+			let mut templates = Templates::default();
+			match templates
+				.env
+				.add_template_owned("post.html", source.to_string())
+				.map_err(template_error)
+			{
+				Err(ParsingError::InvalidTemplate { line, .. }) => line,
+				other => panic!("{:?}", other),
+			}
+		};
+		// Typos in a template are errors rather than crashing the build.
+		assert_eq!(line("a {%} b"), 1);
+		assert_eq!(line("a {#} b"), 1);
+		assert_eq!(line("\n{% if a %}"), 2);
+		assert_eq!(line("{% for a in b %}\n{% endif %}"), 2);
+		assert_eq!(line("\n\n{{ a b }}"), 3);
+	}
+}
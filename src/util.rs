@@ -1,16 +1,24 @@
-use std::{fs, path::Path};
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
 
 use crate::Result;
 
-/// Copy a directory and all its contents recursively.
-pub fn copy_dir<A: AsRef<Path>, B: AsRef<Path>>(from: A, to: B) -> Result<()> {
+/// Copy a directory and all its contents recursively, skipping files `keep`
+/// returns false for.
+pub fn copy_dir<A: AsRef<Path>, B: AsRef<Path>>(
+	from: A,
+	to: B,
+	keep: &dyn Fn(&Path) -> bool,
+) -> Result<()> {
 	fs::create_dir_all(&to)?;
 	for entry in fs::read_dir(&from)? {
 		let entry = entry?;
 		let ty = entry.file_type()?;
 		if ty.is_dir() {
-			copy_dir(entry.path(), to.as_ref().join(entry.file_name()))?;
-		} else {
+			copy_dir(entry.path(), to.as_ref().join(entry.file_name()), keep)?;
+		} else if keep(&entry.path()) {
 			fs::copy(entry.path(), to.as_ref().join(entry.file_name()))?;
 		}
 	}
@@ -18,6 +26,21 @@ pub fn copy_dir<A: AsRef<Path>, B: AsRef<Path>>(from: A, to: B) -> Result<()> {
 	Ok(())
 }
 
+/// Every file in a directory and its subdirectories, sorted.
+pub fn files_in<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
+	let mut files = Vec::new();
+	for entry in fs::read_dir(&dir)? {
+		let entry = entry?;
+		if entry.file_type()?.is_dir() {
+			files.extend(files_in(entry.path())?);
+		} else {
+			files.push(entry.path());
+		}
+	}
+	files.sort();
+	Ok(files)
+}
+
 pub struct Siblings<'a, T> {
 	src: &'a Vec<T>,
 	i: usize,
@@ -48,10 +71,70 @@ impl<'a, T> Iterator for Siblings<'a, T> {
 		if i == len - 1 {
 			return Some((Some(&self.src[i - 1]), &self.src[i], None));
 		}
-		return Some((
-			Some(&self.src[i - 1]),
-			&self.src[i],
-			Some(&self.src[i + 1]),
-		));
+		Some((Some(&self.src[i - 1]), &self.src[i], Some(&self.src[i + 1])))
+	}
+}
+
+/// Turn a name into something fit for a url, like `Teaching Myself Rust` into
+/// `teaching-myself-rust`. Dashes and underscores are kept.
+pub fn slugify(name: &str) -> String {
+	name
+		.to_lowercase()
+		.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+		.filter(|word| !word.is_empty())
+		.collect::<Vec<_>>()
+		.join("-")
+}
+
+/// Slugify each `/` separated part of a name on its own, so `Rust/Game Dev`
+/// becomes `rust/game-dev`. Empty parts are dropped.
+pub fn slugify_path(name: &str) -> String {
+	name
+		.split('/')
+		.map(slugify)
+		.filter(|part| !part.is_empty())
+		.collect::<Vec<_>>()
+		.join("/")
+}
+
+/// 64 bit FNV-1a hash. Not for anything security related, just for telling
+/// when a file has changed.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for byte in bytes {
+		hash ^= *byte as u64;
+		hash = hash.wrapping_mul(0x100000001b3);
+	}
+	hash
+}
+
+/// Map a site url like `/static/embeds/post/Untitled%201.png` back to the file
+/// it was copied from in `./static`. Returns `None` for anything that isn't a
+/// local static asset.
+pub fn url_to_static_path(url: &str) -> Option<PathBuf> {
+	let path = url.split(['?', '#']).next()?;
+	let path = path.strip_prefix("/static/")?;
+	Some(Path::new("./static").join(percent_decode(path)))
+}
+
+/// Decode `%XX` escapes in a url path. Invalid escapes are left as-is.
+pub fn percent_decode(input: &str) -> String {
+	let bytes = input.as_bytes();
+	let mut out = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		if bytes[i] == b'%'
+			&& i + 2 < bytes.len()
+			&& bytes[i + 1].is_ascii_hexdigit()
+			&& bytes[i + 2].is_ascii_hexdigit()
+		{
+			let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+			out.push(u8::from_str_radix(hex, 16).unwrap());
+			i += 3;
+			continue;
+		}
+		out.push(bytes[i]);
+		i += 1;
 	}
+	String::from_utf8_lossy(&out).to_string()
 }